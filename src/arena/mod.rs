@@ -0,0 +1,361 @@
+use bevy::{
+    ecs::system::SystemParam,
+    math::vec2,
+    prelude::{
+        App, Commands, EventReader, IntoSystemConfigs, Plugin, Query, Res, ResMut, Resource,
+        Transform, Update, Vec2, With,
+    },
+    time::Time,
+};
+use bevy_rapier2d::prelude::CollisionEvent;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collider_layer::CollisionLayer,
+    config::structs::{GenerationConfig, SaveConfig},
+    controls::control_state::ControlState,
+    generation::{
+        env_description::{EnvironmentDescription, Shape, ShapeDescription},
+        environment::Environment,
+        lineage::{assign_ids, LineageLog},
+        normalise, select_builders,
+        stats::{Objectives, StatsLog},
+    },
+    handles::Handles,
+    organism::{
+        joint::Joint,
+        organism_list::{update_brains, OrganismList, ScoredBuilders},
+    },
+    scene_manager::is_arena,
+    task::{get_centre, TaskState},
+};
+
+// Direction and distance to the nearest opponent
+pub const NUM_ARENA_STIMULI: usize = 3;
+
+pub struct ArenaPlugin;
+impl Plugin for ArenaPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Arena::default()).add_systems(
+            Update,
+            (
+                update_arena.before(update_brains),
+                handle_arena_contacts,
+                handle_arena_generation,
+            )
+                .run_if(is_arena),
+        );
+    }
+}
+
+#[derive(Resource, Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ArenaConfig {
+    // Evolve predators and prey together instead of a single population
+    pub enable: bool,
+    pub num_predators: usize,
+    pub num_prey: usize,
+    // Width of the arena floor, walls stop organisms leaving
+    pub width: f32,
+    // Distance between organisms of the same population when spawned
+    pub spacing: f32,
+    // Distance between the two populations when spawned
+    pub separation: f32,
+    // Predator score for each touch of a prey
+    pub touch_reward: f32,
+    // Prey score for each second survived without being touched
+    pub survival_weight: f32,
+    // Score for average distance to the nearest opponent, rewarded to prey and
+    // taken from predators
+    pub distance_weight: f32,
+}
+impl Default for ArenaConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            num_predators: 10,
+            num_prey: 20,
+            width: 3000.0,
+            spacing: 40.0,
+            separation: 1200.0,
+            touch_reward: 100.0,
+            survival_weight: 10.0,
+            distance_weight: 0.1,
+        }
+    }
+}
+impl ArenaConfig {
+    pub fn get_num_organisms(&self) -> usize {
+        return self.num_predators + self.num_prey;
+    }
+
+    // A floor walled at both ends with predators spawned on the left and prey on the right
+    pub fn get_environment(&self) -> EnvironmentDescription {
+        let floor_height = 20.0;
+        let wall_height = 400.0;
+        let mut description = EnvironmentDescription::flat(self.width, floor_height, wall_height);
+        // The flat floor starts 200 to the left of the origin and is walled at the back already
+        description.shapes.push(ShapeDescription::new(
+            Shape::Box {
+                extents: vec2(floor_height, wall_height),
+            },
+            vec2(self.width - 200.0, wall_height * 0.5),
+            0.7,
+        ));
+
+        let spawn_y = floor_height + 40.0;
+        for i in 0..self.num_predators {
+            description
+                .spawn_points
+                .push(vec2(i as f32 * self.spacing, spawn_y));
+        }
+        for i in 0..self.num_prey {
+            description
+                .spawn_points
+                .push(vec2(self.separation + i as f32 * self.spacing, spawn_y));
+        }
+        return description;
+    }
+}
+
+// Scores of both populations for one generation
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArenaRecord {
+    pub generation: u32,
+    pub predator_scores: Vec<f32>,
+    pub prey_scores: Vec<f32>,
+}
+
+// Split between the populations and their history, saved with the organisms
+#[derive(Serialize, Deserialize)]
+pub struct ArenaSave {
+    pub num_predators: usize,
+    pub history: Vec<ArenaRecord>,
+}
+
+// Progress of the running arena round, organisms are indexed as in the organism list
+// with predators first
+#[derive(Resource, Default)]
+pub struct Arena {
+    pub active: bool,
+    pub num_predators: usize,
+    touches: Vec<u32>,
+    caught_time: Vec<Option<f32>>,
+    dist_sum: Vec<f32>,
+    ticks: u32,
+    pub history: Vec<ArenaRecord>,
+}
+impl Arena {
+    pub fn reset(&mut self, num_predators: usize, num_organisms: usize) {
+        self.active = true;
+        self.num_predators = num_predators;
+        self.touches = vec![0; num_organisms];
+        self.caught_time = vec![None; num_organisms];
+        self.dist_sum = vec![0.0; num_organisms];
+        self.ticks = 0;
+    }
+
+    fn is_predator(&self, i: usize) -> bool {
+        return i < self.num_predators;
+    }
+
+    // Scores of the round that just finished, predators then prey
+    fn get_scores(&self, ac: &ArenaConfig, duration: f32) -> (Vec<f32>, Vec<f32>) {
+        let ticks = self.ticks.max(1) as f32;
+        let mut predator_scores = vec![];
+        let mut prey_scores = vec![];
+        for i in 0..self.touches.len() {
+            let mean_dist = self.dist_sum[i] / ticks;
+            if self.is_predator(i) {
+                predator_scores.push(
+                    self.touches[i] as f32 * ac.touch_reward
+                        + (ac.width - mean_dist).max(0.0) * ac.distance_weight,
+                );
+            } else {
+                let survived = self.caught_time[i].unwrap_or(duration);
+                prey_scores.push(survived * ac.survival_weight + mean_dist * ac.distance_weight);
+            }
+        }
+        return (predator_scores, prey_scores);
+    }
+
+    pub fn get_save(&self) -> ArenaSave {
+        return ArenaSave {
+            num_predators: self.num_predators,
+            history: self.history.clone(),
+        };
+    }
+}
+
+// Give each organism the direction and distance to its nearest opponent
+fn update_arena(
+    ol: Res<OrganismList>,
+    mut arena: ResMut<Arena>,
+    mut ts: ResMut<TaskState>,
+    joints: Query<&Transform, With<Joint>>,
+) {
+    let num_organisms = ol.organisms.len();
+    if arena.touches.len() != num_organisms {
+        return;
+    }
+    ts.stimuli
+        .resize(num_organisms, vec![0.0; NUM_ARENA_STIMULI]);
+
+    let centres = ol
+        .organisms
+        .iter()
+        .map(|o| get_centre(o, &joints))
+        .collect::<Vec<Vec2>>();
+    for i in 0..num_organisms {
+        let nearest = (0..num_organisms)
+            .filter(|j| arena.is_predator(*j) != arena.is_predator(i))
+            .map(|j| centres[j] - centres[i])
+            .min_by(|a, b| a.length().total_cmp(&b.length()));
+        let to_opponent = match nearest {
+            Some(v) => v,
+            None => continue,
+        };
+
+        arena.dist_sum[i] += to_opponent.length();
+        ts.stimuli[i] = vec![
+            to_opponent.normalize_or_zero().x,
+            to_opponent.normalize_or_zero().y,
+            (to_opponent.length() / 1000.0).min(1.0),
+        ];
+    }
+    arena.ticks += 1;
+}
+
+// Count predators touching prey
+fn handle_arena_contacts(
+    mut events: EventReader<CollisionEvent>,
+    layers: Query<&CollisionLayer>,
+    gc: Res<GenerationConfig>,
+    mut arena: ResMut<Arena>,
+) {
+    for event in events.iter() {
+        let (a, b) = match event {
+            CollisionEvent::Started(a, b, _) => (a, b),
+            CollisionEvent::Stopped(..) => continue,
+        };
+        let (layer_a, layer_b) = match (layers.get(*a), layers.get(*b)) {
+            (Ok(la), Ok(lb)) => (la.layer as usize, lb.layer as usize),
+            _ => continue,
+        };
+        let (predator, prey) = match (arena.is_predator(layer_a), arena.is_predator(layer_b)) {
+            (true, false) => (layer_a, layer_b),
+            (false, true) => (layer_b, layer_a),
+            _ => continue,
+        };
+        if prey >= arena.touches.len() {
+            continue;
+        }
+
+        arena.touches[predator] += 1;
+        arena.caught_time[prey].get_or_insert(gc.timer.elapsed_secs());
+    }
+}
+
+// Resources used to save and score the arena at the end of each round
+#[derive(SystemParam)]
+pub struct ArenaResources<'w> {
+    arena: ResMut<'w, Arena>,
+    ac: Res<'w, ArenaConfig>,
+    sc: Res<'w, SaveConfig>,
+    ts: ResMut<'w, TaskState>,
+    cs: ResMut<'w, ControlState>,
+    lineage: Res<'w, LineageLog>,
+    log: ResMut<'w, StatsLog>,
+}
+
+// Select each population separately and respawn both at the end of a round
+pub fn handle_arena_generation(
+    mut commands: Commands,
+    mut gc: ResMut<GenerationConfig>,
+    time: Res<Time>,
+    handles: Res<Handles>,
+    mut ol: ResMut<OrganismList>,
+    env: Res<Environment>,
+    mut r: ArenaResources,
+) {
+    gc.timer.tick(time.delta());
+    let elapsed_secs = gc.timer.elapsed_secs();
+
+    if ol.builders.is_empty() {
+        return;
+    }
+
+    if gc.unfreeze_flag && elapsed_secs > 0.1 {
+        gc.unfreeze_flag = false;
+        ol.unfreeze();
+    }
+
+    if gc.timer.finished() {
+        let duration = gc.timer.duration().as_secs_f32();
+        gc.timer.reset();
+        gc.timer.unpause();
+        gc.unfreeze_flag = true;
+
+        let (predator_scores, prey_scores) = r.arena.get_scores(&r.ac, duration);
+        r.arena.history.push(ArenaRecord {
+            generation: gc.cur_generation,
+            predator_scores: predator_scores.clone(),
+            prey_scores: prey_scores.clone(),
+        });
+        let mut scores = predator_scores.clone();
+        scores.extend(prey_scores.iter());
+        r.lineage.write(&r.sc, &ol.builders, &scores);
+
+        // Log the fitness each population is selected by, scores are normalised per population
+        let mut fitness = normalise(&predator_scores);
+        fitness.extend(normalise(&prey_scores));
+        let objectives = Objectives {
+            energy: ol.organisms.iter().map(|o| o.energy_used).collect(),
+            ..Default::default()
+        };
+        r.log.sim_time += duration;
+        let row = r.log.get_row(
+            gc.cur_generation,
+            &scores,
+            &fitness,
+            &objectives,
+            &ol.builders,
+        );
+        r.log.write(&r.sc, &row);
+        if gc.cur_generation % r.sc.rate == 0 && r.sc.enable {
+            r.cs.save = true;
+            ol.scored = Some(ScoredBuilders {
+                generation: gc.cur_generation,
+                builders: ol.builders.clone(),
+                scores,
+            });
+        }
+
+        // Despawn current generation
+        ol.despawn(&mut commands);
+        r.ts.stimuli.clear();
+
+        let split = r.arena.num_predators;
+        let mut rng = gc.get_rng();
+        let mut new_builders = select_builders(
+            &ol.builders[..split],
+            &normalise(&predator_scores),
+            split,
+            &mut rng,
+        );
+        new_builders.extend(select_builders(
+            &ol.builders[split..],
+            &normalise(&prey_scores),
+            prey_scores.len(),
+            &mut rng,
+        ));
+        gc.cur_generation += 1;
+        assign_ids(&mut new_builders, gc.cur_generation);
+
+        // Spawn new generation
+        let num_organisms = new_builders.len();
+        ol.set_builders(new_builders);
+        r.arena.reset(split, num_organisms);
+        ol.spawn(&mut commands, &handles, &gc, &env, NUM_ARENA_STIMULI);
+    }
+}
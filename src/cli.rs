@@ -0,0 +1,129 @@
+use std::fs;
+
+use crate::{
+    config::{structs::Config, CONFIG_PATH},
+    controls::save_file::{SaveFile, SaveFormat},
+    generation::lineage::get_ancestry_dot,
+    replay::{export::export, headless::record_save, recording::Recording},
+};
+
+// Commands that run instead of the simulation, returns false when the arguments aren't one,
+// a command that fails exits with a non-zero code so scripts can tell
+pub fn run(args: &[String]) -> bool {
+    let command = match args.get(1) {
+        Some(c) => c.as_str(),
+        None => return false,
+    };
+    match command {
+        "convert" => convert(&args[2..]),
+        "lineage" => lineage(&args[2..]),
+        "export" => export_motion(&args[2..]),
+        _ => return false,
+    }
+    return true;
+}
+
+// Convert a save between JSON and binary, the format is picked from the output's extension
+// and older saves are migrated to the current version on the way
+fn convert(args: &[String]) {
+    let (input, output) = match args {
+        [input, output] => (input, output),
+        _ => {
+            println!("Usage: joint_sim convert <input> <output.json|output.bin>");
+            std::process::exit(1);
+        }
+    };
+
+    let save = match SaveFile::load(input) {
+        Ok(s) => s,
+        Err(err) => {
+            println!("Error loading {:?}, {:?}", input, err);
+            std::process::exit(1);
+        }
+    };
+    let bytes = match save.to_bytes(SaveFormat::from_path(output)) {
+        Ok(b) => b,
+        Err(err) => {
+            println!("Error converting {:?}, {:?}", input, err);
+            std::process::exit(1);
+        }
+    };
+    match fs::write(output, bytes) {
+        Ok(_) => println!("Converted {:?} to {:?}", input, output),
+        Err(err) => {
+            println!("Error writing {:?}, {:?}", output, err);
+            std::process::exit(1);
+        }
+    }
+}
+
+// Write every ancestor of an organism in a lineage file to a Graphviz DOT file, organism
+// ids are stored with each organism in saves
+fn lineage(args: &[String]) {
+    let (lineage_path, id, output) = match args {
+        [lineage_path, id, output] => match id.parse::<u64>() {
+            Ok(id) => (lineage_path, id, output),
+            Err(_) => {
+                println!("Organism id {:?} isn't a number", id);
+                std::process::exit(1);
+            }
+        },
+        _ => {
+            println!("Usage: joint_sim lineage <lineage.jsonl> <organism id> <output.dot>");
+            std::process::exit(1);
+        }
+    };
+
+    let dot = match get_ancestry_dot(lineage_path, id) {
+        Ok(d) => d,
+        Err(err) => {
+            println!("Error reading lineage, {}", err);
+            std::process::exit(1);
+        }
+    };
+    match fs::write(output, dot) {
+        Ok(_) => println!("Wrote the ancestry of {} to {:?}", id, output),
+        Err(err) => {
+            println!("Error writing {:?}, {:?}", output, err);
+            std::process::exit(1);
+        }
+    }
+}
+
+// Draw a recording, or a save simulated without a window, as an animated SVG or a folder of
+// PNG frames, the frame rate and viewport come from the replay section of the settings
+fn export_motion(args: &[String]) {
+    let (input, output) = match args {
+        [input, output] => (input, output),
+        _ => {
+            println!("Usage: joint_sim export <recording.replay|save> <output.svg|folder>");
+            std::process::exit(1);
+        }
+    };
+
+    let recording = match input.ends_with(".replay") {
+        true => Recording::load(input),
+        false => record_save(input),
+    };
+    let recording = match recording {
+        Ok(r) => r,
+        Err(err) => {
+            println!("Error recording {:?}, {:?}", input, err);
+            std::process::exit(1);
+        }
+    };
+    let config = match Config::load_cfg(CONFIG_PATH) {
+        Ok(c) => c.replay.export,
+        Err(err) => {
+            println!("Error loading {:?}, {}", CONFIG_PATH, err);
+            std::process::exit(1);
+        }
+    };
+    match export(&recording, &config, output) {
+        Ok(_) => println!("Exported {:?} to {:?}", input, output),
+        Err(err) => {
+            println!("Error exporting {:?}, {:?}", input, err);
+            std::process::exit(1);
+        }
+    }
+}
@@ -1,22 +1,119 @@
 use bevy::{
     ecs::system::SystemParam,
-    prelude::{Commands, Component, Plugin, Query},
+    prelude::{Bundle, Component, Plugin, Query},
+};
+use bevy_rapier2d::prelude::{
+    ActiveEvents, ActiveHooks, BevyPhysicsHooks, CollisionGroups, Group, RapierPhysicsPlugin,
+    SolverFlags,
 };
-use bevy_rapier2d::prelude::{BevyPhysicsHooks, SolverFlags};
 
-// just some skele code to maybe setup layers at some point
+// Collision groups
+// Organisms only ever collide with the environment, contacts between parts of the same
+// organism are optionally let through by ColliderLayerHook using each organism's layer
 // check here to see an example
 // https://github.com/dimforge/bevy_rapier/blob/a149ff59933f26869482fa3797d1188afecde750/bevy_rapier3d/examples/contact_filter3.rs#L19
+pub const ENVIRONMENT_GROUP: Group = Group::GROUP_1;
+pub const ORGANISM_GROUP: Group = Group::GROUP_2;
+// Organisms on different teams collide with each other, used by the arena
+pub const TEAM_GROUPS: [Group; 2] = [Group::GROUP_3, Group::GROUP_4];
 
 pub struct ColliderLayerPlugin;
 impl Plugin for ColliderLayerPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        todo!()
+        app.add_plugins(RapierPhysicsPlugin::<ColliderLayerHook>::pixels_per_meter(
+            100.0,
+        ));
     }
 }
 
+// Layer of an organism collider, only used to filter contacts when self collision is on
+// The layer is the organism's index in the organism list
 #[derive(Component)]
-pub struct CollisionLayer(pub u32);
+pub struct CollisionLayer {
+    pub layer: u32,
+    pub team: Option<usize>,
+}
+
+// Describes how an organism's colliders should collide
+#[derive(Clone, Copy)]
+pub struct OrganismCollision {
+    pub layer: u32,
+    pub self_collision: bool,
+    // Organisms on a team collide with organisms on the other team
+    pub team: Option<usize>,
+}
+impl OrganismCollision {
+    pub fn new(layer: u32, self_collision: bool) -> Self {
+        return Self {
+            layer,
+            self_collision,
+            team: None,
+        };
+    }
+
+    pub fn with_team(mut self, team: usize) -> Self {
+        self.team = Some(team);
+        return self;
+    }
+
+    pub fn get_bundle(&self) -> OrganismCollisionBundle {
+        let mut memberships = ORGANISM_GROUP;
+        let mut filters = ENVIRONMENT_GROUP;
+        let mut active_events = ActiveEvents::empty();
+        if self.self_collision {
+            filters |= ORGANISM_GROUP;
+        }
+        if let Some(team) = self.team {
+            memberships |= TEAM_GROUPS[team % 2];
+            filters |= TEAM_GROUPS[(team + 1) % 2];
+            active_events = ActiveEvents::COLLISION_EVENTS;
+        }
+
+        return OrganismCollisionBundle {
+            collision_groups: CollisionGroups::new(memberships, filters),
+            collision_layer: CollisionLayer {
+                layer: self.layer,
+                team: self.team,
+            },
+            active_hooks: match self.self_collision {
+                true => ActiveHooks::FILTER_CONTACT_PAIRS,
+                false => ActiveHooks::empty(),
+            },
+            active_events,
+        };
+    }
+}
+
+#[derive(Bundle)]
+pub struct OrganismCollisionBundle {
+    collision_groups: CollisionGroups,
+    collision_layer: CollisionLayer,
+    active_hooks: ActiveHooks,
+    active_events: ActiveEvents,
+}
+
+// Collision groups for static and kinematic environment colliders
+pub fn environment_collision_groups() -> CollisionGroups {
+    return CollisionGroups::new(ENVIRONMENT_GROUP, Group::ALL);
+}
+
+// Loose objects that belong to a single organism, they share the organism's layer so they only
+// collide with it and the environment, not with other organisms or their objects
+#[derive(Bundle)]
+pub struct ObjectCollisionBundle {
+    collision_groups: CollisionGroups,
+    collision_layer: CollisionLayer,
+    active_hooks: ActiveHooks,
+}
+impl ObjectCollisionBundle {
+    pub fn new(layer: u32) -> Self {
+        return Self {
+            collision_groups: CollisionGroups::new(ENVIRONMENT_GROUP, Group::ALL),
+            collision_layer: CollisionLayer { layer, team: None },
+            active_hooks: ActiveHooks::FILTER_CONTACT_PAIRS,
+        };
+    }
+}
 
 #[derive(SystemParam)]
 pub struct ColliderLayerHook<'w, 's> {
@@ -28,32 +125,19 @@ impl BevyPhysicsHooks for ColliderLayerHook<'_, '_> {
         context: bevy_rapier2d::prelude::PairFilterContextView,
     ) -> Option<bevy_rapier2d::prelude::SolverFlags> {
         let layer_a = match self.tags.get(context.collider1()) {
-            Ok(col) => col.0,
+            Ok(col) => col,
             Err(_) => return Some(SolverFlags::COMPUTE_IMPULSES),
         };
         let layer_b = match self.tags.get(context.collider2()) {
-            Ok(col) => col.0,
+            Ok(col) => col,
             Err(_) => return Some(SolverFlags::COMPUTE_IMPULSES),
         };
-        if layer_a == layer_b {
+        // Parts of the same organism and organisms on opposing teams collide
+        let opposing_teams = layer_a.team.is_some() && layer_a.team != layer_b.team;
+        if layer_a.layer == layer_b.layer || opposing_teams {
             Some(SolverFlags::COMPUTE_IMPULSES)
         } else {
             None
         }
     }
-
-    // fn filter_intersection_pair(
-    //     &self,
-    //     _context: bevy_rapier2d::prelude::PairFilterContextView,
-    // ) -> bool {
-    //     false
-    // }
-
-    // fn modify_solver_contacts(
-    //     &self,
-    //     _context: bevy_rapier2d::prelude::ContactModificationContextView,
-    // ) {
-    // }
 }
-
-fn setup_physics(mut commands: Commands) {}
@@ -7,3 +7,13 @@ pub const TERTIARY: Color = Color::hsl(228.0, 0.392, 0.49);
 
 pub const NOT_SELECTED: Color = Color::hsl(358.0, 1.0, 0.80);
 pub const SELECTED: Color = Color::hsl(115.0, 1.0, 0.80);
+
+// Organism parts, also used when drawing replays and exporting
+pub const JOINT: Color = Color::hsl(115.0, 0.60, 0.35);
+pub const BONE: Color = Color::hsl(0.0, 0.50, 0.90);
+pub const MUSCLE_CONTRACT: Color = Color::hsl(0.0, 0.60, 0.45);
+pub const MUSCLE_EXPAND: Color = Color::hsl(240.0, 0.60, 0.45);
+pub const MUSCLE_NEUTRAL: Color = Color::hsl(300.0, 0.60, 0.45);
+
+// Floors, walls and obstacles
+pub const ENVIRONMENT: Color = Color::BLACK;
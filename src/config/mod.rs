@@ -1,16 +1,64 @@
-use bevy::prelude::Plugin;
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::{Plugin, Res},
+};
 
-use crate::config::structs::Config;
+use crate::{
+    arena::ArenaConfig,
+    config::structs::{CameraConfig, Config, EnvironmentConfig, GenerationConfig, SaveConfig},
+    replay::ReplayConfig,
+    task::TaskConfig,
+};
 
 pub mod structs;
 
+pub const CONFIG_PATH: &str = "settings.cfg";
+
 pub struct ConfigPlugin;
 impl Plugin for ConfigPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        let mut c = Config::load_cfg("settings.cfg");
+        let mut c = match Config::load_cfg(CONFIG_PATH) {
+            Ok(c) => c,
+            Err(err) => {
+                println!("Error loading {:?}, {}", CONFIG_PATH, err);
+                std::process::exit(1);
+            }
+        };
         c.generation.reset_timer();
+        if c.generation.seed.is_none() {
+            c.generation.seed = Some(rand::random());
+        }
         app.insert_resource(c.generation)
             .insert_resource(c.camera)
-            .insert_resource(c.save);
+            .insert_resource(c.save)
+            .insert_resource(c.environment)
+            .insert_resource(c.task)
+            .insert_resource(c.arena)
+            .insert_resource(c.replay);
+    }
+}
+
+// Every config resource, used to put the config back together
+#[derive(SystemParam)]
+pub struct ConfigResources<'w> {
+    pub cc: Res<'w, CameraConfig>,
+    pub gc: Res<'w, GenerationConfig>,
+    pub sc: Res<'w, SaveConfig>,
+    pub ec: Res<'w, EnvironmentConfig>,
+    pub tc: Res<'w, TaskConfig>,
+    pub ac: Res<'w, ArenaConfig>,
+    pub rc: Res<'w, ReplayConfig>,
+}
+impl ConfigResources<'_> {
+    pub fn get_config(&self) -> Config {
+        return Config {
+            camera: self.cc.clone(),
+            generation: self.gc.clone(),
+            save: self.sc.clone(),
+            environment: self.ec.clone(),
+            task: self.tc.clone(),
+            arena: self.ac.clone(),
+            replay: self.rc.clone(),
+        };
     }
 }
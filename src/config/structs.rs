@@ -3,15 +3,30 @@ use bevy::{
     prelude::{Resource, Vec2},
     time::{Timer, TimerMode},
 };
-use core::panic;
+use rand::{rngs::StdRng, SeedableRng};
 use serde::{Deserialize, Serialize};
-use std::fs::File;
-use std::io::BufReader;
 use std::time::Duration;
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::{
+    fs::{self, File},
+    io::BufReader,
+    path::Path,
+};
 
-use crate::organism::genome::Genome;
+use crate::{
+    arena::ArenaConfig,
+    controls::save_file::SaveFormat,
+    generation::{
+        curriculum::CurriculumConfig, env_description::EnvironmentDescription, stats::StatsFormat,
+        terrain::TerrainConfig, trial::TrialConfig,
+    },
+    organism::{fluid::MediumConfig, genome::Genome, perturbation::PerturbationConfig},
+    replay::ReplayConfig,
+    task::TaskConfig,
+};
 
 #[derive(Resource, Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct CameraConfig {
     pub move_modifier: f32,
     pub zoom_modifier: f32,
@@ -30,6 +45,7 @@ impl Default for CameraConfig {
 }
 
 #[derive(Resource, Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct GenerationConfig {
     pub num_organisms: usize,
     pub vertical_sep: f32,
@@ -39,6 +55,15 @@ pub struct GenerationConfig {
     pub timer: Timer,
     pub unfreeze_flag: bool,
     pub debug_flag: bool,
+    // Let parts of the same organism collide with each other
+    pub self_collision: bool,
+    // How many times each organism is evaluated per generation
+    pub trials: TrialConfig,
+    pub fitness_direction: FitnessDirection,
+    // Pushes, sensor noise and actuator delay
+    pub perturbation: PerturbationConfig,
+    // Seed for selection and mutation, picked randomly when not set
+    pub seed: Option<u64>,
 }
 impl Default for GenerationConfig {
     fn default() -> Self {
@@ -50,10 +75,21 @@ impl Default for GenerationConfig {
             timer: Default::default(),
             unfreeze_flag: true,
             debug_flag: false,
+            self_collision: false,
+            trials: Default::default(),
+            fitness_direction: FitnessDirection::PositiveX,
+            perturbation: Default::default(),
+            seed: None,
         }
     }
 }
 impl GenerationConfig {
+    // Reseeded every generation so a run can continue from a save without storing rng state
+    pub fn get_rng(&self) -> StdRng {
+        let seed = self.seed.unwrap_or(0);
+        return StdRng::seed_from_u64(seed ^ (self.cur_generation as u64).wrapping_mul(7919));
+    }
+
     pub fn reset_timer(&mut self) {
         self.timer = Timer::new(
             Duration::from_secs_f32(self.generation_duration),
@@ -63,12 +99,29 @@ impl GenerationConfig {
 }
 
 #[derive(Resource, Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct SaveConfig {
     pub enable: bool,
     pub folder: String,
     pub rate: u32,
     pub load_save: bool,
     pub load_file: String,
+    // Organism file to seed the run with, it joins the loaded population when there is one
+    pub import_file: Option<String>,
+    // Format new saves are written in, loading works out the format from the file
+    pub format: SaveFormat,
+    // Checkpoints from this run that are kept, the rest are removed as new ones are saved
+    pub keep_last: usize,
+    // Also keep every checkpoint from a generation that's a multiple of this, 0 for none
+    pub keep_every: u32,
+    pub keep_best: bool,
+    // Save the running generation when the app is closed
+    pub autosave_on_exit: bool,
+    // Append a row of stats to a file in the folder after every generation
+    pub log_stats: bool,
+    pub stats_format: StatsFormat,
+    // Append the id, parents and mutations of every organism to a file in the folder
+    pub log_lineage: bool,
 }
 impl Default for SaveConfig {
     fn default() -> Self {
@@ -78,9 +131,86 @@ impl Default for SaveConfig {
             rate: 20,
             load_save: false,
             load_file: "saved_generations/test_0/14-09-2023_23-24_gen251.json".to_string(),
+            import_file: None,
+            format: SaveFormat::Json,
+            keep_last: 5,
+            keep_every: 100,
+            keep_best: true,
+            autosave_on_exit: true,
+            log_stats: true,
+            stats_format: StatsFormat::Csv,
+            log_lineage: true,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum FitnessDirection {
+    // Distance travelled to the right
+    PositiveX,
+    // Distance the centre travelled to the right of the spawn point, jittered spawn points
+    // don't change the score
+    PositiveXFromSpawn,
+    // Distance travelled from the spawn point in any direction, used for swimmers
+    Any,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum EnvironmentLayout {
+    // Every organism gets its own floor stacked vertically
+    Lanes,
+    // Every organism runs on the same floor overlaid on top of each other
+    Shared,
+}
+
+#[derive(Resource, Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct EnvironmentConfig {
+    pub layout: EnvironmentLayout,
+    // Alpha organisms are drawn with when sharing a floor
+    pub shared_alpha: f32,
+    pub terrain: TerrainConfig,
+    // Air or fluid the organisms move through
+    pub medium: MediumConfig,
+    // Terrain difficulty stages advanced as the population improves
+    pub curriculum: CurriculumConfig,
+    // Size of the flat floor and the wall at its back end, terrain uses the same wall
+    pub floor_width: f32,
+    pub floor_height: f32,
+    pub wall_height: f32,
+    // JSON environment description, replaces terrain and flat floors when set
+    pub file: Option<String>,
+    // Rebuild the environment when the file changes
+    pub hot_reload: bool,
+}
+impl Default for EnvironmentConfig {
+    fn default() -> Self {
+        Self {
+            layout: EnvironmentLayout::Lanes,
+            shared_alpha: 0.15,
+            terrain: Default::default(),
+            medium: Default::default(),
+            curriculum: Default::default(),
+            floor_width: 4000.0,
+            floor_height: 20.0,
+            wall_height: 200.0,
+            file: None,
+            hot_reload: true,
         }
     }
 }
+impl EnvironmentConfig {
+    pub fn get_floor(&self) -> EnvironmentDescription {
+        return EnvironmentDescription::flat(self.floor_width, self.floor_height, self.wall_height);
+    }
+
+    pub fn get_organism_alpha(&self) -> f32 {
+        return match self.layout {
+            EnvironmentLayout::Lanes => 1.0,
+            EnvironmentLayout::Shared => self.shared_alpha,
+        };
+    }
+}
 
 // defunct for now
 #[derive(Resource, Debug, Serialize, Deserialize, Clone)]
@@ -89,11 +219,16 @@ pub struct GenomeConfig {
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct Config {
     pub camera: CameraConfig,
     pub generation: GenerationConfig,
     // pub control: ControlConfig,
     pub save: SaveConfig,
+    pub environment: EnvironmentConfig,
+    pub task: TaskConfig,
+    pub arena: ArenaConfig,
+    pub replay: ReplayConfig,
 }
 impl Default for Config {
     fn default() -> Self {
@@ -101,28 +236,377 @@ impl Default for Config {
             camera: Default::default(),
             generation: Default::default(),
             save: Default::default(),
+            environment: Default::default(),
+            task: Default::default(),
+            arena: Default::default(),
+            replay: Default::default(),
         }
     }
 }
 impl Config {
     #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
-    pub fn load_cfg(path: &str) -> Config {
-        return Config::default();
+    pub fn load_cfg(path: &str) -> Result<Config, ConfigError> {
+        return Ok(Config::default());
     }
 
+    // Missing fields are filled from the defaults and a default file is written when there
+    // isn't one
     #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
-    pub fn load_cfg(path: &str) -> Config {
-        let file = File::open(path);
-        if let Err(err) = file {
-            panic!("Error opening file {:?}", err);
+    pub fn load_cfg(path: &str) -> Result<Config, ConfigError> {
+        if !Path::new(path).exists() {
+            let config = Config::default();
+            config.save_cfg(path)?;
+            println!("No config found, wrote the defaults to {:?}", path);
+            return Ok(config);
         }
 
-        let reader = BufReader::new(file.unwrap());
+        let reader = BufReader::new(File::open(path)?);
+        let config: Config = serde_json::from_reader(reader)?;
+        config.validate()?;
+        return Ok(config);
+    }
 
-        let json: Result<Config, serde_json::Error> = serde_json::from_reader(reader);
-        match json {
-            Ok(cfg) => return cfg,
-            Err(err) => panic!("Error reading JSON {:?}", err),
+    // Indented the same as the hand written settings file
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn save_cfg(&self, path: &str) -> Result<(), ConfigError> {
+        let mut json = vec![];
+        let formatter = serde_json::ser::PrettyFormatter::with_indent(b"    ");
+        let mut serializer = serde_json::Serializer::with_formatter(&mut json, formatter);
+        self.serialize(&mut serializer)?;
+        fs::write(path, json)?;
+        return Ok(());
+    }
+
+    // Every invalid value is reported at once with its field path
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut errors = vec![];
+        let mut check = |valid: bool, field: &str, reason: &str| {
+            if !valid {
+                errors.push(format!("{} {}", field, reason));
+            }
+        };
+        let above_zero = "must be above 0";
+        let not_negative = "can't be negative";
+        let fraction = "must be between 0 and 1";
+        let is_fraction = |v: f32| (0.0..=1.0).contains(&v);
+
+        let cc = &self.camera;
+        check(cc.zoom_modifier > 0.0, "camera.zoom_modifier", above_zero);
+        check(cc.starting_zoom > 0.0, "camera.starting_zoom", above_zero);
+
+        let gc = &self.generation;
+        check(gc.num_organisms > 0, "generation.num_organisms", above_zero);
+        check(
+            gc.vertical_sep >= 0.0,
+            "generation.vertical_sep",
+            not_negative,
+        );
+        check(
+            gc.generation_duration > 0.0,
+            "generation.generation_duration",
+            above_zero,
+        );
+        check(gc.trials.trials > 0, "generation.trials.trials", above_zero);
+        check(
+            gc.trials.spawn_jitter >= 0.0,
+            "generation.trials.spawn_jitter",
+            not_negative,
+        );
+        let pc = &gc.perturbation;
+        // Push strengths are drawn from 0 to the max even when pushes are off
+        check(
+            pc.max_impulse >= 0.0,
+            "generation.perturbation.max_impulse",
+            not_negative,
+        );
+        if pc.enable {
+            check(
+                pc.start_delay >= 0.0,
+                "generation.perturbation.start_delay",
+                not_negative,
+            );
+            check(
+                pc.interval > 0.0,
+                "generation.perturbation.interval",
+                above_zero,
+            );
+            check(
+                pc.interval_jitter >= 0.0,
+                "generation.perturbation.interval_jitter",
+                not_negative,
+            );
+            check(
+                pc.sensor_noise >= 0.0,
+                "generation.perturbation.sensor_noise",
+                not_negative,
+            );
+            check(
+                pc.recovery_time >= 0.0,
+                "generation.perturbation.recovery_time",
+                not_negative,
+            );
+        }
+
+        let sc = &self.save;
+        check(sc.rate > 0, "save.rate", above_zero);
+        check(
+            !sc.enable || !sc.folder.is_empty(),
+            "save.folder",
+            "can't be empty when saving",
+        );
+        check(
+            !sc.load_save || !sc.load_file.is_empty(),
+            "save.load_file",
+            "can't be empty",
+        );
+
+        let ec = &self.environment;
+        check(
+            is_fraction(ec.shared_alpha),
+            "environment.shared_alpha",
+            fraction,
+        );
+        check(ec.floor_width > 0.0, "environment.floor_width", above_zero);
+        check(
+            ec.floor_height > 0.0,
+            "environment.floor_height",
+            above_zero,
+        );
+        check(ec.wall_height > 0.0, "environment.wall_height", above_zero);
+        let tc = &ec.terrain;
+        check(
+            !ec.curriculum.enable || tc.enable,
+            "environment.curriculum.enable",
+            "needs environment.terrain.enable",
+        );
+        if tc.enable {
+            check(tc.length > 0.0, "environment.terrain.length", above_zero);
+            check(
+                tc.segment_len > 0.0,
+                "environment.terrain.segment_len",
+                above_zero,
+            );
+            check(
+                is_fraction(tc.step_chance),
+                "environment.terrain.step_chance",
+                fraction,
+            );
+            check(
+                is_fraction(tc.gap_chance),
+                "environment.terrain.gap_chance",
+                fraction,
+            );
+            check(
+                tc.gap_width > 0.0,
+                "environment.terrain.gap_width",
+                above_zero,
+            );
+            check(
+                tc.gap_depth >= 0.0,
+                "environment.terrain.gap_depth",
+                not_negative,
+            );
+            check(
+                tc.friction >= 0.0,
+                "environment.terrain.friction",
+                not_negative,
+            );
+        }
+        if ec.curriculum.enable {
+            check(
+                !ec.curriculum.stages.is_empty(),
+                "environment.curriculum.stages",
+                "is empty",
+            );
         }
+        let mut last_threshold = f32::MIN;
+        for (i, stage) in ec.curriculum.stages.iter().enumerate() {
+            let field = |name: &str| format!("environment.curriculum.stages[{}].{}", i, name);
+            check(
+                stage.threshold >= last_threshold,
+                &field("threshold"),
+                "can't be below the previous stage's",
+            );
+            last_threshold = stage.threshold;
+            let chances = [
+                ("step_chance", stage.step_chance),
+                ("gap_chance", stage.gap_chance),
+            ];
+            for (name, chance) in chances {
+                check(chance.map_or(true, is_fraction), &field(name), fraction);
+            }
+            let lengths = [
+                ("step_height", stage.step_height),
+                ("friction", stage.friction),
+            ];
+            for (name, length) in lengths {
+                check(
+                    length.map_or(true, |l| l >= 0.0),
+                    &field(name),
+                    not_negative,
+                );
+            }
+            check(
+                stage.gap_width.map_or(true, |w| w > 0.0),
+                &field("gap_width"),
+                above_zero,
+            );
+        }
+
+        let goal = &self.task.goal;
+        check(goal.min_dist >= 0.0, "task.goal.min_dist", not_negative);
+        check(
+            goal.max_dist >= goal.min_dist,
+            "task.goal.max_dist",
+            "can't be below min_dist",
+        );
+        check(
+            goal.reach_radius > 0.0,
+            "task.goal.reach_radius",
+            above_zero,
+        );
+        check(goal.max_targets > 0, "task.goal.max_targets", above_zero);
+        check(
+            self.task.climb.step_width > 0.0,
+            "task.climb.step_width",
+            above_zero,
+        );
+        let carry = &self.task.carry;
+        check(
+            carry.object_radius > 0.0,
+            "task.carry.object_radius",
+            above_zero,
+        );
+        check(
+            carry.object_density > 0.0,
+            "task.carry.object_density",
+            above_zero,
+        );
+        check(
+            carry.reach_radius > 0.0,
+            "task.carry.reach_radius",
+            above_zero,
+        );
+
+        let ac = &self.arena;
+        if ac.enable {
+            check(ac.num_predators > 0, "arena.num_predators", above_zero);
+            check(ac.num_prey > 0, "arena.num_prey", above_zero);
+            check(ac.width > 0.0, "arena.width", above_zero);
+        }
+
+        let rc = &self.replay;
+        check(rc.record_best != Some(0), "replay.record_best", above_zero);
+        check(rc.export.fps > 0.0, "replay.export.fps", above_zero);
+        check(rc.export.width > 0, "replay.export.width", above_zero);
+        check(rc.export.height > 0, "replay.export.height", above_zero);
+        if let Some([x0, y0, x1, y1]) = rc.export.viewport {
+            check(
+                x0 < x1 && y0 < y1,
+                "replay.export.viewport",
+                "min must be below max",
+            );
+        }
+
+        if errors.is_empty() {
+            return Ok(());
+        }
+        return Err(ConfigError::Invalid(errors));
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+    // Field path and reason of every invalid value
+    Invalid(Vec<String>),
+}
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return match self {
+            ConfigError::Io(err) => write!(f, "couldn't read or write the config, {}", err),
+            ConfigError::Parse(err) => write!(f, "couldn't parse the config, {}", err),
+            ConfigError::Invalid(errors) => {
+                write!(f, "invalid config values")?;
+                for e in errors.iter() {
+                    write!(f, "\n    {}", e)?;
+                }
+                Ok(())
+            }
+        };
+    }
+}
+impl std::error::Error for ConfigError {}
+impl From<std::io::Error> for ConfigError {
+    fn from(err: std::io::Error) -> Self {
+        return ConfigError::Io(err);
+    }
+}
+impl From<serde_json::Error> for ConfigError {
+    fn from(err: serde_json::Error) -> Self {
+        return ConfigError::Parse(err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generation::curriculum::CurriculumStage;
+
+    fn get_errors(config: &Config) -> Vec<String> {
+        return match config.validate() {
+            Err(ConfigError::Invalid(errors)) => errors,
+            _ => vec![],
+        };
+    }
+
+    #[test]
+    fn default_is_valid() {
+        assert!(Config::default().validate().is_ok());
+    }
+
+    #[test]
+    fn bad_values_are_rejected() {
+        let mut config = Config::default();
+        config.generation.num_organisms = 0;
+        config.environment.terrain.enable = true;
+        config.environment.terrain.gap_width = 0.0;
+        config.environment.terrain.gap_depth = -1.0;
+        config.environment.curriculum.stages = vec![CurriculumStage {
+            gap_width: Some(0.0),
+            ..Default::default()
+        }];
+
+        let errors = get_errors(&config);
+        let fields = [
+            "generation.num_organisms",
+            "environment.terrain.gap_width",
+            "environment.terrain.gap_depth",
+            "environment.curriculum.stages[0].gap_width",
+        ];
+        assert_eq!(errors.len(), fields.len());
+        for field in fields {
+            assert!(errors.iter().any(|e| e.starts_with(field)), "{}", field);
+        }
+    }
+
+    #[test]
+    fn missing_fields_use_defaults() {
+        let json = r#"{ "generation": { "num_organisms": 12 }, "environment": {} }"#;
+        let config: Config = serde_json::from_str(json).unwrap();
+        let default = Config::default();
+
+        assert_eq!(config.generation.num_organisms, 12);
+        assert_eq!(
+            config.generation.generation_duration,
+            default.generation.generation_duration
+        );
+        assert_eq!(
+            config.environment.terrain.gap_width,
+            default.environment.terrain.gap_width
+        );
+        assert_eq!(config.save.rate, default.save.rate);
+        assert!(config.validate().is_ok());
     }
 }
@@ -1,6 +1,10 @@
-use crate::{config::structs::CameraConfig, controls::control_state::ControlState};
+use crate::{
+    config::structs::{CameraConfig, EnvironmentConfig, EnvironmentLayout},
+    controls::control_state::ControlState,
+    organism::joint::Joint,
+};
 
-use bevy::prelude::*;
+use bevy::{prelude::*, window::PrimaryWindow};
 
 #[derive(Component)]
 pub struct ScrollingCam;
@@ -21,6 +25,48 @@ pub fn translate_cam(
     }
 }
 
+// Keep every organism in view when they share a floor
+pub fn frame_pack(
+    mut cam: Query<(&mut Transform, &mut OrthographicProjection), With<ScrollingCam>>,
+    joints: Query<&Transform, (With<Joint>, Without<ScrollingCam>)>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cs: Res<ControlState>,
+    ec: Res<EnvironmentConfig>,
+) {
+    if ec.layout != EnvironmentLayout::Shared || !cs.follow_pack {
+        return;
+    }
+    let (mut t, mut op) = match cam.get_single_mut() {
+        Ok(c) => c,
+        Err(_) => return,
+    };
+    let window = match windows.get_single() {
+        Ok(w) => w,
+        Err(_) => return,
+    };
+
+    let mut min = Vec2::MAX;
+    let mut max = Vec2::MIN;
+    for jt in joints.iter() {
+        min = min.min(jt.translation.truncate());
+        max = max.max(jt.translation.truncate());
+    }
+    if min.x > max.x {
+        return;
+    }
+
+    // Pad the bounding box so organisms aren't touching the screen edge
+    let padding = 100.0;
+    let size = (max - min) + Vec2::splat(padding * 2.0);
+    let target_scale = f32::max(size.x / window.width(), size.y / window.height()).max(0.5);
+    let target_translation = ((min + max) * 0.5).extend(t.translation.z);
+
+    // Ease towards the target to avoid jitter
+    let ease = 0.1;
+    t.translation = t.translation.lerp(target_translation, ease);
+    op.scale += (target_scale - op.scale) * ease;
+}
+
 pub fn spawn_cam(mut commands: Commands, cc: Res<CameraConfig>) {
     commands.spawn((
         Camera2dBundle {
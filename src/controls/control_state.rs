@@ -13,6 +13,15 @@ pub struct ControlState {
     pub double_click: bool,
     pub world_mouse_pos: Vec2,
     pub save: bool,
+    pub cycle_joint_type: bool,
+    pub switch_joint_end: bool,
+    pub limit_delta: f32,
+    pub follow_pack: bool,
+    pub export_organism: bool,
+    // Replay playback
+    pub toggle_pause: bool,
+    pub scrub_delta: f32,
+    pub speed_delta: f32,
 }
 impl Default for ControlState {
     fn default() -> Self {
@@ -24,6 +33,14 @@ impl Default for ControlState {
             double_click: false,
             world_mouse_pos: Vec2::ZERO,
             save: false,
+            cycle_joint_type: false,
+            switch_joint_end: false,
+            limit_delta: 0.0,
+            follow_pack: true,
+            export_organism: false,
+            toggle_pause: false,
+            scrub_delta: 0.0,
+            speed_delta: 0.0,
         }
     }
 }
@@ -45,6 +62,17 @@ pub struct ControlConfig {
     zoom_in: KeyCode,
     zoom_out: KeyCode,
     save: KeyCode,
+    cycle_joint_type: KeyCode,
+    switch_joint_end: KeyCode,
+    widen_limits: KeyCode,
+    narrow_limits: KeyCode,
+    follow_pack: KeyCode,
+    export_organism: KeyCode,
+    pause: KeyCode,
+    scrub_back: KeyCode,
+    scrub_forward: KeyCode,
+    speed_up: KeyCode,
+    slow_down: KeyCode,
     double_click_window: f32,
 }
 impl Default for ControlConfig {
@@ -57,6 +85,17 @@ impl Default for ControlConfig {
             zoom_in: KeyCode::Up,
             zoom_out: KeyCode::Down,
             save: KeyCode::P,
+            cycle_joint_type: KeyCode::J,
+            switch_joint_end: KeyCode::K,
+            widen_limits: KeyCode::E,
+            narrow_limits: KeyCode::Q,
+            follow_pack: KeyCode::F,
+            export_organism: KeyCode::X,
+            pause: KeyCode::Space,
+            scrub_back: KeyCode::Left,
+            scrub_forward: KeyCode::Right,
+            speed_up: KeyCode::Period,
+            slow_down: KeyCode::Comma,
             double_click_window: 0.3,
         }
     }
@@ -140,6 +179,42 @@ pub fn update_control_state(
         cs.save = true;
     }
 
+    cs.cycle_joint_type = keyboard.just_pressed(cc.cycle_joint_type);
+    cs.switch_joint_end = keyboard.just_pressed(cc.switch_joint_end);
+    let mut ld = 0.0;
+    if keyboard.just_pressed(cc.widen_limits) {
+        ld += 1.0;
+    }
+    if keyboard.just_pressed(cc.narrow_limits) {
+        ld -= 1.0;
+    }
+    cs.limit_delta = ld;
+
+    if keyboard.just_pressed(cc.follow_pack) {
+        cs.follow_pack = !cs.follow_pack;
+    }
+    if !cs.export_organism && keyboard.just_pressed(cc.export_organism) {
+        cs.export_organism = true;
+    }
+
+    cs.toggle_pause = keyboard.just_pressed(cc.pause);
+    let mut sd = 0.0;
+    if keyboard.pressed(cc.scrub_back) {
+        sd -= 1.0;
+    }
+    if keyboard.pressed(cc.scrub_forward) {
+        sd += 1.0;
+    }
+    cs.scrub_delta = sd;
+    let mut spd = 0.0;
+    if keyboard.just_pressed(cc.speed_up) {
+        spd += 1.0;
+    }
+    if keyboard.just_pressed(cc.slow_down) {
+        spd -= 1.0;
+    }
+    cs.speed_delta = spd;
+
     if td != Vec2::ZERO {
         cs.translate_delta = td * camera_config.move_modifier;
     }
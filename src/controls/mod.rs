@@ -1,24 +1,42 @@
-use bevy::prelude::{App, Plugin, Startup, Update};
+use bevy::prelude::{App, Condition, IntoSystemConfigs, Last, Plugin, Startup, Update};
+
+use crate::scene_manager::{is_arena, is_simulation};
 
 use self::{
-    camera::{spawn_cam, translate_cam},
+    camera::{frame_pack, spawn_cam, translate_cam},
     control_state::{update_control_state, ControlConfig, ControlState},
-    save::save_generation,
+    organism_export::{draw_picked_organism, export_organism, pick_organism},
+    save::{autosave_on_exit, save_generation, spawn_save_status, update_save_status, SaveManager},
 };
 
 pub mod camera;
 pub mod control_state;
+pub mod organism_export;
 pub mod save;
+pub mod save_file;
 
 pub struct ControlPlugin;
 impl Plugin for ControlPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(ControlState::default())
             .insert_resource(ControlConfig::default())
-            .add_systems(Startup, spawn_cam)
+            .insert_resource(SaveManager::default())
+            .add_systems(Startup, (spawn_cam, spawn_save_status))
+            .add_systems(
+                Update,
+                (
+                    update_control_state,
+                    translate_cam,
+                    save_generation,
+                    update_save_status,
+                ),
+            )
+            .add_systems(Last, autosave_on_exit)
+            .add_systems(Update, frame_pack.run_if(is_simulation))
             .add_systems(
                 Update,
-                (update_control_state, translate_cam, save_generation),
+                (pick_organism, draw_picked_organism, export_organism)
+                    .run_if(is_simulation.or_else(is_arena)),
             );
     }
 }
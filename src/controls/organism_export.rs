@@ -0,0 +1,112 @@
+use bevy::prelude::{Gizmos, Query, Res, ResMut, Transform, Vec2, With};
+
+use crate::{
+    color_palette,
+    config::structs::GenerationConfig,
+    organism::{joint::Joint, library::LIBRARY_FOLDER, organism_list::OrganismList},
+};
+
+use super::{control_state::ControlState, save::SaveManager};
+
+// How close to one of its joints a click has to be to pick an organism
+const PICK_RADIUS: f32 = 20.0;
+
+// Click an organism to pick it for exporting, clicking anywhere else unpicks it
+pub fn pick_organism(
+    mut cs: ResMut<ControlState>,
+    mut ol: ResMut<OrganismList>,
+    joints: Query<&Transform, With<Joint>>,
+) {
+    if !cs.left_mouse_up {
+        return;
+    }
+    cs.left_mouse_up = false;
+
+    let mouse_pos = cs.world_mouse_pos;
+    let mut closest: Option<(usize, f32)> = None;
+    for (i, o) in ol.organisms.iter().enumerate() {
+        for t in o.joints.iter().filter_map(|j| joints.get(*j).ok()) {
+            let dist = t.translation.truncate().distance(mouse_pos);
+            if dist < PICK_RADIUS && closest.map_or(true, |(_, d)| dist < d) {
+                closest = Some((i, dist));
+            }
+        }
+    }
+    ol.selected = closest.map(|(i, _)| i);
+}
+
+// Circle the picked organism
+pub fn draw_picked_organism(
+    mut gizmos: Gizmos,
+    ol: Res<OrganismList>,
+    joints: Query<&Transform, With<Joint>>,
+) {
+    let o = match ol.selected.and_then(|i| ol.organisms.get(i)) {
+        Some(o) => o,
+        None => return,
+    };
+    let positions = o
+        .joints
+        .iter()
+        .filter_map(|j| joints.get(*j).ok())
+        .map(|t| t.translation.truncate())
+        .collect::<Vec<Vec2>>();
+    if positions.is_empty() {
+        return;
+    }
+
+    let center = positions.iter().sum::<Vec2>() / positions.len() as f32;
+    let radius = positions
+        .iter()
+        .map(|p| p.distance(center))
+        .fold(0.0, f32::max);
+    gizmos.circle_2d(center, radius + PICK_RADIUS, color_palette::SELECTED);
+}
+
+// Write the picked organism, or the best of the last generation when none is picked, to the
+// organism library so it can be shared and imported
+pub fn export_organism(
+    mut cs: ResMut<ControlState>,
+    ol: Res<OrganismList>,
+    gc: Res<GenerationConfig>,
+    mut sm: ResMut<SaveManager>,
+) {
+    if !cs.export_organism {
+        return;
+    }
+    cs.export_organism = false;
+
+    let num_builders = ol.builders.len().max(1);
+    let (builder, fitness) = match ol.selected {
+        // Organisms are spawned once for every trial running in parallel
+        Some(i) => (ol.builders.get(i % num_builders), None),
+        None => match &ol.best {
+            Some((b, s)) => (Some(b), Some(*s)),
+            None => (None, None),
+        },
+    };
+    let builder = match builder {
+        Some(b) => b,
+        None => {
+            sm.status = "Pick an organism to export".to_string();
+            return;
+        }
+    };
+
+    let generation = builder.lineage.birth_generation;
+    let index = builder.lineage.id & u32::MAX as u64;
+    let description = match fitness {
+        Some(f) => format!(
+            "Best of generation {} with a score of {:.2}",
+            gc.cur_generation.saturating_sub(1),
+            f
+        ),
+        None => format!("Picked during generation {}", gc.cur_generation),
+    };
+    let name = format!("Generation {} #{}", generation, index);
+    let path = format!("{}/gen{}_{}.json", LIBRARY_FOLDER, generation, index);
+    match builder.to_blueprint(name, description, fitness).save(&path) {
+        Ok(_) => sm.status = format!("Exported organism to {:?}", path),
+        Err(err) => sm.status = format!("Error exporting organism, {}", err),
+    }
+}
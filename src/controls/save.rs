@@ -1,51 +1,210 @@
-use std::{fs::File, io::Write};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
-use bevy::prelude::{Res, ResMut};
+use bevy::{
+    app::AppExit,
+    asset::Error,
+    ecs::system::SystemParam,
+    prelude::{
+        default, Commands, Component, EventReader, Query, Res, ResMut, Resource, TextBundle, With,
+    },
+    text::{Text, TextStyle},
+    ui::{PositionType, Style, Val},
+};
 
 use crate::{
-    config::structs::{GenerationConfig, SaveConfig},
+    arena::Arena,
+    color_palette,
+    config::{structs::SaveConfig, ConfigResources},
+    generation::{curriculum::Curriculum, stats::GenerationStats},
     organism::organism_list::OrganismList,
 };
 
-use super::control_state::ControlState;
+use super::{
+    control_state::ControlState,
+    save_file::{SaveFile, SaveFormat, SavedOrganism, SAVE_VERSION},
+};
 
-pub fn save_generation(
-    ol: Res<OrganismList>,
-    mut cs: ResMut<ControlState>,
-    gc: Res<GenerationConfig>,
-    sc: Res<SaveConfig>,
-) {
-    if cs.save && sc.enable {
-        cs.save = false;
-        let json = match serde_json::to_string(&ol.builders) {
-            Ok(res) => res,
+// A checkpoint written during this run
+struct Checkpoint {
+    path: PathBuf,
+    generation: u32,
+}
+
+// Writes checkpoints and removes old ones, only files written during this run are ever
+// removed
+#[derive(Resource, Default)]
+pub struct SaveManager {
+    // Oldest first
+    checkpoints: Vec<Checkpoint>,
+    best_score: Option<f32>,
+    best_path: Option<PathBuf>,
+    // Result of the last save, shown in the corner of the screen
+    pub status: String,
+}
+impl SaveManager {
+    fn get_path(sc: &SaveConfig, generation: u32) -> PathBuf {
+        let time = chrono::offset::Local::now().format("%d-%m-%Y_%H-%M");
+        let extension = sc.format.get_extension();
+        return Path::new(&sc.folder).join(format!("{}_gen{}.{}", time, generation, extension));
+    }
+
+    // Save then remove checkpoints that are no longer kept, the status is updated either way
+    pub fn save(&mut self, sc: &SaveConfig, save: &SaveFile) {
+        let path = Self::get_path(sc, save.generation);
+        match Self::write(&path, save, sc.format) {
+            Ok(_) => self.status = format!("Saved generation {}", save.generation),
             Err(err) => {
-                println!("Error saving generation, {:?}", err);
+                self.status = format!("Error saving generation {}, {}", save.generation, err);
                 return;
             }
-        };
+        }
 
-        let path = format!(
-            "{}\\{}_gen{}.json",
-            sc.folder,
-            chrono::offset::Local::now()
-                .format("%d-%m-%Y_%H-%M")
-                .to_string(),
-            gc.cur_generation,
-        );
-        let mut file = match File::create(path) {
-            Ok(f) => f,
-            Err(err) => {
-                println!("Error creating file to save to, {:?}", err);
-                return;
+        let best = save
+            .organisms
+            .iter()
+            .filter_map(|o| o.fitness)
+            .fold(None, |a: Option<f32>, b| Some(a.map_or(b, |a| a.max(b))));
+        if let Some(best) = best {
+            if self.best_score.map_or(true, |s| best > s) {
+                self.best_score = Some(best);
+                self.best_path = Some(path.clone());
             }
-        };
-        match file.write_all(json.as_bytes()) {
-            Ok(_) => {}
-            Err(err) => {
-                println!("Error writing to file, {:?}", err);
-                return;
+        }
+
+        self.checkpoints.push(Checkpoint {
+            path,
+            generation: save.generation,
+        });
+        self.rotate(sc);
+    }
+
+    // Write to a temporary file first so a crash can't leave a half written save
+    fn write(path: &Path, save: &SaveFile, format: SaveFormat) -> Result<(), Error> {
+        if let Some(folder) = path.parent() {
+            fs::create_dir_all(folder)?;
+        }
+        let bytes = save.to_bytes(format)?;
+        let temp_path = path.with_extension(format!("{}.tmp", format.get_extension()));
+        fs::write(&temp_path, bytes)?;
+        fs::rename(&temp_path, path)?;
+        return Ok(());
+    }
+
+    // Keep the last few checkpoints, every nth generation and the best
+    fn rotate(&mut self, sc: &SaveConfig) {
+        let num_checkpoints = self.checkpoints.len();
+        let checkpoints = std::mem::take(&mut self.checkpoints);
+        for (i, c) in checkpoints.into_iter().enumerate() {
+            let recent = i + sc.keep_last >= num_checkpoints;
+            let milestone = sc.keep_every > 0 && c.generation % sc.keep_every == 0;
+            let best = sc.keep_best && self.best_path.as_ref() == Some(&c.path);
+            if recent || milestone || best {
+                self.checkpoints.push(c);
+                continue;
+            }
+            if let Err(err) = fs::remove_file(&c.path) {
+                self.status = format!("Error removing {:?}, {}", c.path, err);
             }
         }
     }
 }
+
+// Everything written to a checkpoint
+#[derive(SystemParam)]
+pub struct SaveResources<'w> {
+    ol: ResMut<'w, OrganismList>,
+    config: ConfigResources<'w>,
+    curriculum: Res<'w, Curriculum>,
+    arena: Res<'w, Arena>,
+    stats: Res<'w, GenerationStats>,
+    sm: ResMut<'w, SaveManager>,
+}
+impl SaveResources<'_> {
+    fn save(&mut self) {
+        // Save the last evaluated generation with its scores and the population selected
+        // from it when there is one
+        let (generation, builders, fitness, population) = match self.ol.scored.take() {
+            Some(s) => (
+                s.generation,
+                s.builders,
+                s.scores.into_iter().map(Some).collect(),
+                self.ol.builders.clone(),
+            ),
+            None => (
+                self.config.gc.cur_generation,
+                self.ol.builders.clone(),
+                vec![None; self.ol.builders.len()],
+                vec![],
+            ),
+        };
+        let save = SaveFile {
+            version: SAVE_VERSION,
+            created: chrono::offset::Local::now().to_rfc3339(),
+            config: Some(self.config.get_config()),
+            generation,
+            seed: self.config.gc.seed.unwrap_or(0),
+            organisms: SavedOrganism::from_builders(builders, fitness),
+            population,
+            curriculum: self.curriculum.clone(),
+            stats: self.stats.history.clone(),
+            arena: self.arena.active.then(|| self.arena.get_save()),
+        };
+        self.sm.save(&self.config.sc, &save);
+    }
+}
+
+pub fn save_generation(mut cs: ResMut<ControlState>, mut r: SaveResources) {
+    if cs.save && r.config.sc.enable {
+        cs.save = false;
+        r.save();
+    }
+}
+
+// Save the running generation when the app is closed
+pub fn autosave_on_exit(mut exit: EventReader<AppExit>, mut r: SaveResources) {
+    if exit.iter().next().is_none() {
+        return;
+    }
+    let sc = &r.config.sc;
+    if sc.enable && sc.autosave_on_exit && !r.ol.builders.is_empty() {
+        r.save();
+    }
+}
+
+#[derive(Component)]
+pub struct SaveStatusLabel;
+
+pub fn spawn_save_status(mut commands: Commands) {
+    commands.spawn((
+        SaveStatusLabel,
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 24.0,
+                color: color_palette::TERTIARY,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            right: Val::Px(10.0),
+            bottom: Val::Px(10.0),
+            ..default()
+        }),
+    ));
+}
+
+pub fn update_save_status(
+    sm: Res<SaveManager>,
+    mut labels: Query<&mut Text, With<SaveStatusLabel>>,
+) {
+    if !sm.is_changed() {
+        return;
+    }
+    for mut t in labels.iter_mut() {
+        t.sections[0].value = sm.status.clone();
+    }
+}
@@ -0,0 +1,425 @@
+use bevy::asset::Error;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::fs;
+
+use crate::{
+    arena::{ArenaRecord, ArenaSave},
+    config::structs::Config,
+    generation::{curriculum::Curriculum, stats::GenerationRecord},
+    organism::organism::OrganismBuilder,
+};
+
+// Bumped whenever the layout of a save changes, older versions are migrated when loaded
+// 0: bare array of builders, or predators and prey from the arena, with the curriculum
+//    in a separate file
+// 1: SaveFile
+// 2: next population and generation stats so runs can be resumed exactly, config is
+//    optional for migrated saves
+pub const SAVE_VERSION: u32 = 2;
+
+// Start of every binary save, followed by the version and the gzipped bincode save
+const BINARY_MAGIC: &[u8] = b"JSIMSAVE";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum SaveFormat {
+    Json,
+    // Compressed bincode, much smaller and faster for large populations
+    Binary,
+}
+impl SaveFormat {
+    pub fn get_extension(&self) -> &'static str {
+        return match self {
+            SaveFormat::Json => "json",
+            SaveFormat::Binary => "bin",
+        };
+    }
+
+    // Binary for .bin files, JSON otherwise
+    pub fn from_path(path: &str) -> Self {
+        return match path.ends_with(".bin") {
+            true => SaveFormat::Binary,
+            false => SaveFormat::Json,
+        };
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SavedOrganism {
+    // Lineage id of the organism
+    pub id: u64,
+    // None when the organism hasn't been evaluated yet
+    pub fitness: Option<f32>,
+    pub builder: OrganismBuilder,
+}
+impl SavedOrganism {
+    pub fn from_builders(
+        builders: Vec<OrganismBuilder>,
+        fitness: Vec<Option<f32>>,
+    ) -> Vec<SavedOrganism> {
+        return builders
+            .into_iter()
+            .zip(fitness)
+            .map(|(builder, fitness)| SavedOrganism {
+                id: builder.lineage.id,
+                fitness,
+                builder,
+            })
+            .collect();
+    }
+}
+
+// Everything needed to continue a run from a generation
+#[derive(Serialize, Deserialize)]
+pub struct SaveFile {
+    pub version: u32,
+    pub created: String,
+    // None when migrated from a save that didn't record it
+    pub config: Option<Config>,
+    // Generation the organisms are from
+    pub generation: u32,
+    // Run seed, the rng is reseeded from it and the generation every generation so
+    // this is all the rng state there is
+    pub seed: u64,
+    pub organisms: Vec<SavedOrganism>,
+    // Builders selected from the organisms for the next generation, empty when the
+    // organisms haven't been evaluated yet
+    #[serde(default)]
+    pub population: Vec<OrganismBuilder>,
+    pub curriculum: Curriculum,
+    #[serde(default)]
+    pub stats: Vec<GenerationRecord>,
+    pub arena: Option<ArenaSave>,
+}
+impl SaveFile {
+    // Builders to spawn when resuming
+    pub fn get_population(&self) -> Vec<OrganismBuilder> {
+        if !self.population.is_empty() {
+            return self.population.clone();
+        }
+        return self.organisms.iter().map(|o| o.builder.clone()).collect();
+    }
+
+    // Generation to continue from, one after the organisms when they've been selected from
+    pub fn get_resume_generation(&self) -> u32 {
+        return match self.population.is_empty() {
+            true => self.generation,
+            false => self.generation + 1,
+        };
+    }
+
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    pub fn load(path: &str) -> Result<Self, Error> {
+        return Err(Error::msg(format!("Can't load {} on the web", path)));
+    }
+
+    // Either format is loaded whatever the file is called
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn load(path: &str) -> Result<Self, Error> {
+        let bytes = fs::read(path)?;
+        if bytes.starts_with(BINARY_MAGIC) {
+            return Self::from_binary(&bytes[BINARY_MAGIC.len()..]);
+        }
+        let value: serde_json::Value = serde_json::from_slice(&bytes)?;
+
+        let version = match value.get("version") {
+            Some(v) => v.as_u64().unwrap_or(0) as u32,
+            None => 0,
+        };
+        return match version {
+            0 => Self::migrate_v0(value, path),
+            // Version 1 is missing fields that default to empty
+            1 | SAVE_VERSION => {
+                let mut save: Self = serde_json::from_value(value)?;
+                save.version = SAVE_VERSION;
+                Ok(save)
+            }
+            v => Err(Error::msg(format!(
+                "Save version {} is newer than supported version {}",
+                v, SAVE_VERSION
+            ))),
+        };
+    }
+
+    pub fn to_bytes(&self, format: SaveFormat) -> Result<Vec<u8>, Error> {
+        return match format {
+            SaveFormat::Json => Ok(serde_json::to_vec(self)?),
+            SaveFormat::Binary => {
+                let mut bytes = BINARY_MAGIC.to_vec();
+                bytes.extend(SAVE_VERSION.to_le_bytes());
+                let mut encoder = GzEncoder::new(bytes, Compression::default());
+                bincode::serialize_into(&mut encoder, self)?;
+                Ok(encoder.finish()?)
+            }
+        };
+    }
+
+    // Binary saves aren't self describing so only the current version can be read
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    fn from_binary(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() < 4 {
+            return Err(Error::msg("Binary save is missing its version"));
+        }
+        let version = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        if version != SAVE_VERSION {
+            return Err(Error::msg(format!(
+                "Binary save version {} can't be read by version {}",
+                version, SAVE_VERSION
+            )));
+        }
+        let decoder = GzDecoder::new(&bytes[4..]);
+        return Ok(bincode::deserialize_from(decoder)?);
+    }
+
+    // Version 0 saves only had builders, the generation is taken from the file name and
+    // the curriculum from the file saved next to it
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    fn migrate_v0(value: serde_json::Value, path: &str) -> Result<Self, Error> {
+        let (builders, arena) = match value.is_array() {
+            true => (serde_json::from_value::<Vec<OrganismBuilder>>(value)?, None),
+            false => {
+                let save: ArenaSaveV0 = serde_json::from_value(value)?;
+                let arena = ArenaSave {
+                    num_predators: save.predators.len(),
+                    history: save.history,
+                };
+                let mut builders = save.predators;
+                builders.extend(save.prey);
+                (builders, Some(arena))
+            }
+        };
+
+        let generation = path
+            .trim_end_matches(".json")
+            .rsplit("_gen")
+            .next()
+            .and_then(|g| g.parse::<u32>().ok())
+            .unwrap_or(0);
+        let curriculum = Curriculum::load(&Curriculum::get_path(path)).unwrap_or_default();
+        let fitness = vec![None; builders.len()];
+
+        return Ok(Self {
+            version: SAVE_VERSION,
+            created: String::new(),
+            config: None,
+            generation,
+            seed: 0,
+            organisms: SavedOrganism::from_builders(builders, fitness),
+            population: vec![],
+            curriculum,
+            stats: vec![],
+            arena,
+        });
+    }
+}
+
+// Arena save before the save file was versioned
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+#[derive(Deserialize)]
+struct ArenaSaveV0 {
+    predators: Vec<OrganismBuilder>,
+    prey: Vec<OrganismBuilder>,
+    history: Vec<ArenaRecord>,
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use super::*;
+    use crate::organism::bone::JointType;
+
+    fn get_builder() -> OrganismBuilder {
+        return OrganismBuilder::new(
+            1,
+            vec![4],
+            vec![vec2(0.0, 0.0), vec2(40.0, 0.0), vec2(80.0, 20.0)],
+            vec![[0, 1], [1, 2]],
+            vec![[JointType::Revolute; 2]; 2],
+            vec![[0, 1]],
+            vec![],
+        );
+    }
+
+    // Version 1 save as it was written before populations and stats were saved
+    fn get_v1_save() -> serde_json::Value {
+        return serde_json::json!({
+            "version": 1,
+            "created": "01-01-2024_00-00",
+            "config": Config::default(),
+            "generation": 7,
+            "seed": 42,
+            "organisms": [
+                { "id": 0, "fitness": 1.5, "builder": get_builder() },
+                { "id": 1, "fitness": null, "builder": get_builder() },
+            ],
+            "curriculum": { "stage": 2 },
+            "arena": null,
+        });
+    }
+
+    // Unique file in the temp folder so tests can run in parallel
+    fn get_path(name: &str) -> String {
+        let file = format!("save_file_test_{}_{}", std::process::id(), name);
+        return std::env::temp_dir()
+            .join(file)
+            .to_string_lossy()
+            .to_string();
+    }
+
+    fn write_and_load(name: &str, bytes: &[u8]) -> Result<SaveFile, Error> {
+        let path = get_path(name);
+        fs::write(&path, bytes).unwrap();
+        let save = SaveFile::load(&path);
+        fs::remove_file(&path).unwrap();
+        return save;
+    }
+
+    #[test]
+    fn json_save_is_loaded() {
+        let bytes = serde_json::to_vec(&get_v1_save()).unwrap();
+        let loaded = write_and_load("json_save.json", &bytes).unwrap();
+        assert_eq!(loaded.version, SAVE_VERSION);
+        assert_eq!(loaded.generation, 7);
+        assert_eq!(loaded.seed, 42);
+        assert_eq!(loaded.curriculum.stage, 2);
+        assert!(loaded.arena.is_none());
+        let fitness = loaded
+            .organisms
+            .iter()
+            .map(|o| o.fitness)
+            .collect::<Vec<_>>();
+        assert_eq!(fitness, vec![Some(1.5), None]);
+        assert_eq!(
+            serde_json::to_value(&loaded.organisms[0].builder).unwrap(),
+            serde_json::to_value(get_builder()).unwrap()
+        );
+    }
+
+    #[test]
+    fn newer_version_is_rejected() {
+        let mut value = get_v1_save();
+        value["version"] = (SAVE_VERSION + 1).into();
+        let bytes = serde_json::to_vec(&value).unwrap();
+        assert!(write_and_load("newer_version.json", &bytes).is_err());
+    }
+
+    #[test]
+    fn v0_builders_are_migrated() {
+        let builders = vec![get_builder(), get_builder(), get_builder()];
+        let bytes = serde_json::to_vec(&builders).unwrap();
+        let loaded = write_and_load("run_gen12.json", &bytes).unwrap();
+        assert_eq!(loaded.version, SAVE_VERSION);
+        assert_eq!(loaded.generation, 12);
+        assert!(loaded.arena.is_none());
+        assert_eq!(loaded.organisms.len(), 3);
+        assert!(loaded.organisms.iter().all(|o| o.fitness.is_none()));
+        let migrated = loaded
+            .organisms
+            .iter()
+            .map(|o| &o.builder)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            serde_json::to_value(migrated).unwrap(),
+            serde_json::to_value(builders).unwrap()
+        );
+    }
+
+    #[test]
+    fn v0_arena_is_migrated() {
+        let value = serde_json::json!({
+            "predators": vec![get_builder()],
+            "prey": vec![get_builder(), get_builder()],
+            "history": [],
+        });
+        let bytes = serde_json::to_vec(&value).unwrap();
+        let loaded = write_and_load("arena_gen3.json", &bytes).unwrap();
+        assert_eq!(loaded.generation, 3);
+        assert_eq!(loaded.organisms.len(), 3);
+        assert_eq!(loaded.arena.map(|a| a.num_predators), Some(1));
+    }
+
+    fn get_save() -> SaveFile {
+        let builders = vec![get_builder(), get_builder()];
+        return SaveFile {
+            version: SAVE_VERSION,
+            created: "01-01-2024_00-00".to_string(),
+            config: Some(Config::default()),
+            generation: 7,
+            seed: 42,
+            organisms: SavedOrganism::from_builders(builders.clone(), vec![Some(1.5), None]),
+            population: builders,
+            curriculum: Curriculum { stage: 2 },
+            stats: vec![],
+            arena: None,
+        };
+    }
+
+    fn assert_same(a: &SaveFile, b: &SaveFile) {
+        assert_eq!(a.version, b.version);
+        assert_eq!(a.generation, b.generation);
+        assert_eq!(a.seed, b.seed);
+        assert_eq!(a.curriculum.stage, b.curriculum.stage);
+        assert_eq!(a.get_resume_generation(), b.get_resume_generation());
+        let organisms = |s: &SaveFile| serde_json::to_value(&s.organisms).unwrap();
+        assert_eq!(organisms(a), organisms(b));
+        let population = |s: &SaveFile| serde_json::to_value(&s.population).unwrap();
+        assert_eq!(population(a), population(b));
+    }
+
+    #[test]
+    fn population_round_trip() {
+        let save = get_save();
+        let bytes = serde_json::to_vec(&save).unwrap();
+        let loaded = write_and_load("population_round_trip.json", &bytes).unwrap();
+        assert_same(&save, &loaded);
+        // The population was selected from the organisms so it's the next generation's
+        assert_eq!(loaded.get_resume_generation(), 8);
+    }
+
+    #[test]
+    fn version_1_is_upgraded() {
+        let bytes = serde_json::to_vec(&get_v1_save()).unwrap();
+        let loaded = write_and_load("version_1.json", &bytes).unwrap();
+        assert!(loaded.population.is_empty());
+        assert!(loaded.stats.is_empty());
+        // Organisms that haven't been selected from are resumed as they are
+        assert_eq!(loaded.get_resume_generation(), 7);
+        assert_eq!(loaded.get_population().len(), 2);
+    }
+
+    #[test]
+    fn v0_has_no_config() {
+        let bytes = serde_json::to_vec(&vec![get_builder()]).unwrap();
+        let loaded = write_and_load("no_config_gen1.json", &bytes).unwrap();
+        assert!(loaded.config.is_none());
+        assert_eq!(loaded.get_population().len(), 1);
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let save = get_save();
+        let bytes = save.to_bytes(SaveFormat::Binary).unwrap();
+        assert!(bytes.starts_with(BINARY_MAGIC));
+        // The format is worked out from the contents, not the file name
+        let loaded = write_and_load("binary_round_trip.json", &bytes).unwrap();
+        assert_same(&save, &loaded);
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let save = get_save();
+        let bytes = save.to_bytes(SaveFormat::Json).unwrap();
+        let loaded = write_and_load("json_round_trip.bin", &bytes).unwrap();
+        assert_same(&save, &loaded);
+    }
+
+    #[test]
+    fn binary_other_version_is_rejected() {
+        let mut bytes = get_save().to_bytes(SaveFormat::Binary).unwrap();
+        let version = BINARY_MAGIC.len();
+        bytes[version..version + 4].copy_from_slice(&(SAVE_VERSION + 1).to_le_bytes());
+        assert!(write_and_load("other_version.bin", &bytes).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use std::{fs::File, io::BufReader};
+
+use bevy::{asset::Error, prelude::Resource};
+use serde::{Deserialize, Serialize};
+
+use super::terrain::TerrainConfig;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum CurriculumMetric {
+    // Highest fitness of any organism
+    Best,
+    // Fitness of the median organism
+    Median,
+}
+
+// Terrain overrides for a stage, unset values use the base terrain config
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct CurriculumStage {
+    // Fitness needed to advance to the next stage, the same fitness organisms are selected by
+    pub threshold: f32,
+    #[serde(default)]
+    pub slope: Option<f32>,
+    #[serde(default)]
+    pub step_chance: Option<f32>,
+    #[serde(default)]
+    pub step_height: Option<f32>,
+    #[serde(default)]
+    pub gap_chance: Option<f32>,
+    #[serde(default)]
+    pub gap_width: Option<f32>,
+    #[serde(default)]
+    pub friction: Option<f32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct CurriculumConfig {
+    // Needs terrain enabled, stages only change the generated terrain
+    pub enable: bool,
+    pub metric: CurriculumMetric,
+    pub stages: Vec<CurriculumStage>,
+}
+impl Default for CurriculumConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            metric: CurriculumMetric::Median,
+            stages: vec![],
+        }
+    }
+}
+
+// Progress through the curriculum, saved with each generation
+#[derive(Resource, Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Curriculum {
+    pub stage: usize,
+}
+impl Curriculum {
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    pub fn load(path: &str) -> Result<Self, Error> {
+        return Err(Error::msg(format!("Can't load {} on the web", path)));
+    }
+
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn load(path: &str) -> Result<Self, Error> {
+        let file = File::open(path)?;
+        let reader = BufReader::new(file);
+        let curriculum = serde_json::from_reader(reader)?;
+        return Ok(curriculum);
+    }
+
+    // Path of the curriculum file older saves kept next to a generation
+    pub fn get_path(generation_path: &str) -> String {
+        return format!("{}.curriculum", generation_path);
+    }
+
+    // Terrain config with the current stage's overrides applied
+    pub fn apply(&self, cc: &CurriculumConfig, tc: &TerrainConfig) -> TerrainConfig {
+        let mut tc = tc.clone();
+        if !cc.enable {
+            return tc;
+        }
+        let stage = match cc.stages.get(self.stage) {
+            Some(s) => s,
+            None => return tc,
+        };
+
+        if let Some(slope) = stage.slope {
+            tc.slope = slope;
+        }
+        if let Some(step_chance) = stage.step_chance {
+            tc.step_chance = step_chance;
+        }
+        if let Some(step_height) = stage.step_height {
+            tc.step_height = step_height;
+        }
+        if let Some(gap_chance) = stage.gap_chance {
+            tc.gap_chance = gap_chance;
+        }
+        if let Some(gap_width) = stage.gap_width {
+            tc.gap_width = gap_width;
+        }
+        if let Some(friction) = stage.friction {
+            tc.friction = friction;
+        }
+        return tc;
+    }
+
+    // Advance a stage if the population's fitness beats the current threshold,
+    // returns true if the stage changed
+    pub fn update(&mut self, cc: &CurriculumConfig, fitness: &Vec<f32>) -> bool {
+        if !cc.enable || fitness.is_empty() || self.stage + 1 >= cc.stages.len() {
+            return false;
+        }
+
+        let mut sorted = fitness.clone();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let score = match cc.metric {
+            CurriculumMetric::Best => sorted[sorted.len() - 1],
+            CurriculumMetric::Median => sorted[sorted.len() / 2],
+        };
+
+        if score >= cc.stages[self.stage].threshold {
+            self.stage += 1;
+            println!("Curriculum advanced to stage {}", self.stage);
+            return true;
+        }
+        return false;
+    }
+}
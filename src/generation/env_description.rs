@@ -0,0 +1,136 @@
+use std::{fs::File, io::BufReader};
+
+use bevy::{
+    asset::Error,
+    math::vec2,
+    prelude::{Mesh, Vec2},
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+};
+use serde::{Deserialize, Serialize};
+
+use super::terrain::build_terrain_mesh;
+
+// Describes the static and moving parts of an environment, positions are relative to a lane
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct EnvironmentDescription {
+    #[serde(default)]
+    pub shapes: Vec<ShapeDescription>,
+    // Where organisms spawn, organisms are spread across multiple spawn points
+    #[serde(default)]
+    pub spawn_points: Vec<Vec2>,
+    // X positions organisms need to cross
+    #[serde(default)]
+    pub finish_lines: Vec<f32>,
+}
+impl EnvironmentDescription {
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    pub fn load(path: &str) -> Result<Self, Error> {
+        return Err(Error::msg(format!("Can't load {} on the web", path)));
+    }
+
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn load(path: &str) -> Result<Self, Error> {
+        let file = File::open(path)?;
+        let reader = BufReader::new(file);
+        let description = serde_json::from_reader(reader)?;
+        return Ok(description);
+    }
+
+    // Flat floor with a wall at its back end, used when there is no terrain or environment file
+    pub fn flat(width: f32, height: f32, wall_height: f32) -> Self {
+        return Self {
+            shapes: vec![
+                ShapeDescription::new(
+                    Shape::Box {
+                        extents: vec2(width, height),
+                    },
+                    vec2((width / 2.0) - 200.0, 0.0),
+                    0.7,
+                ),
+                Self::get_back_wall(height, wall_height),
+            ],
+            spawn_points: vec![],
+            finish_lines: vec![],
+        };
+    }
+
+    // Wall at the start of floors and terrain, 200 behind the spawn point, so organisms can't
+    // walk off the back
+    pub fn get_back_wall(floor_height: f32, wall_height: f32) -> ShapeDescription {
+        return ShapeDescription::new(
+            Shape::Box {
+                extents: vec2(floor_height, wall_height),
+            },
+            vec2(-200.0, wall_height * 0.5),
+            0.7,
+        );
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShapeDescription {
+    pub shape: Shape,
+    #[serde(default)]
+    pub translation: Vec2,
+    // Rotation in radians
+    #[serde(default)]
+    pub rotation: f32,
+    #[serde(default = "default_friction")]
+    pub friction: f32,
+    #[serde(default)]
+    pub restitution: f32,
+    // Kinematic shapes move at a constant velocity, static shapes don't move
+    #[serde(default)]
+    pub motion: Option<KinematicMotion>,
+}
+impl ShapeDescription {
+    pub fn new(shape: Shape, translation: Vec2, friction: f32) -> Self {
+        return Self {
+            shape,
+            translation,
+            rotation: 0.0,
+            friction,
+            restitution: 0.0,
+            motion: None,
+        };
+    }
+}
+
+fn default_friction() -> f32 {
+    return 0.7;
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct KinematicMotion {
+    #[serde(default)]
+    pub linvel: Vec2,
+    #[serde(default)]
+    pub angvel: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum Shape {
+    Box { extents: Vec2 },
+    // Right angled triangle rising to the right
+    Ramp { width: f32, height: f32 },
+    Polyline { points: Vec<Vec2> },
+    Circle { radius: f32 },
+}
+impl Shape {
+    // Mesh for shapes that can't be drawn by scaling a shared mesh
+    pub fn build_mesh(&self) -> Option<Mesh> {
+        return match self {
+            Shape::Box { .. } | Shape::Circle { .. } => None,
+            Shape::Ramp { width, height } => {
+                let positions = vec![[0.0, 0.0, 0.0], [*width, 0.0, 0.0], [*width, *height, 0.0]];
+                let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+                mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+                mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 0.0, 1.0]; 3]);
+                mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; 3]);
+                mesh.set_indices(Some(Indices::U32(vec![0, 1, 2])));
+                Some(mesh)
+            }
+            Shape::Polyline { points } => Some(build_terrain_mesh(points)),
+        };
+    }
+}
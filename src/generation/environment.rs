@@ -1,90 +1,384 @@
+use std::time::SystemTime;
+
 use bevy::{
     math::vec2,
-    prelude::{default, Bundle, Commands, Entity, Handle, Resource, Transform, Vec2},
+    prelude::{
+        default, Assets, Bundle, Commands, Entity, Handle, Local, Mesh, Quat, Query, Res, ResMut,
+        Resource, Transform, Vec2, Vec3, With,
+    },
     sprite::{ColorMaterial, MaterialMesh2dBundle, Mesh2dHandle},
+    time::Time,
+};
+use bevy_rapier2d::prelude::{
+    Collider, CollisionGroups, Friction, Restitution, RigidBody, Velocity,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    collider_layer::environment_collision_groups,
+    config::structs::{EnvironmentConfig, EnvironmentLayout, GenerationConfig},
+    handles::Handles,
+    organism::{joint::Joint, organism_list::OrganismList},
 };
-use bevy_rapier2d::prelude::{Collider, Friction, RigidBody};
 
-use crate::config::structs::GenerationConfig;
+use super::{
+    curriculum::Curriculum,
+    env_description::{EnvironmentDescription, Shape, ShapeDescription},
+    terrain::generate_terrain,
+};
 
 #[derive(Resource)]
 pub struct Environment {
     env_ents: Vec<Entity>,
+    // One description for each trial being run at the same time
+    descriptions: Vec<EnvironmentDescription>,
+    // Trials to spawn environments for
+    pub trials: Vec<usize>,
+    // Environment required by the current task, used instead of terrain and flat floors
+    pub task_description: Option<EnvironmentDescription>,
+    layout: EnvironmentLayout,
+    // Lanes each description is spawned in
+    lanes_per_group: usize,
+    vertical_sep: f32,
+    // Environment files that were loaded and when they were last modified, used for hot reloading
+    files_modified: Vec<(String, Option<SystemTime>)>,
 }
 impl Environment {
     pub fn new() -> Self {
-        return Self { env_ents: vec![] };
+        return Self {
+            env_ents: vec![],
+            descriptions: vec![],
+            trials: vec![0],
+            task_description: None,
+            layout: EnvironmentLayout::Lanes,
+            lanes_per_group: 1,
+            vertical_sep: 0.0,
+            files_modified: vec![],
+        };
+    }
+
+    pub fn get_finish_lines(&self, group: usize) -> Option<&Vec<f32>> {
+        return self.descriptions.get(group).map(|d| &d.finish_lines);
+    }
+
+    // Spawn point of an organism running in a group's environment
+    pub fn get_spawn(&self, group: usize, index: usize) -> Vec2 {
+        let spawn_points = &self.descriptions[group].spawn_points;
+        let spawn = spawn_points[index % spawn_points.len()];
+        return spawn + self.get_lane_offset(group, index);
+    }
+
+    // Offset of the lane an organism runs in, the group's shapes are spawned at every lane
+    pub fn get_lane_offset(&self, group: usize, index: usize) -> Vec2 {
+        let lane = match self.layout {
+            EnvironmentLayout::Lanes => group * self.lanes_per_group + index,
+            EnvironmentLayout::Shared => group,
+        };
+        return vec2(0.0, lane as f32 * self.vertical_sep);
+    }
+
+    pub fn get_description(&self, group: usize) -> Option<&EnvironmentDescription> {
+        return self.descriptions.get(group);
+    }
+
+    // Build the description of a single lane from the environment file, the task, terrain or a
+    // flat floor
+    fn load_description(
+        &mut self,
+        gc: &GenerationConfig,
+        ec: &EnvironmentConfig,
+        curriculum: &Curriculum,
+        trial: usize,
+    ) -> EnvironmentDescription {
+        let tc = &curriculum.apply(&ec.curriculum, &ec.terrain);
+        let mut seed = tc.get_seed(gc.cur_generation);
+        if gc.trials.vary_seed {
+            seed = seed.wrapping_add(trial as u64 * 7919);
+        }
+
+        let mut description = None;
+        if let Some(path) = gc.trials.get_file(trial).or(ec.file.as_ref()) {
+            match EnvironmentDescription::load(path) {
+                Ok(d) => description = Some(d),
+                Err(err) => println!("Error loading environment {:?}, {:?}", path, err),
+            }
+            self.files_modified.push((path.clone(), get_modified(path)));
+        }
+
+        let mut description = match description.or_else(|| self.task_description.clone()) {
+            Some(d) => d,
+            None if tc.enable => EnvironmentDescription {
+                shapes: vec![
+                    ShapeDescription::new(
+                        Shape::Polyline {
+                            points: generate_terrain(tc, seed),
+                        },
+                        vec2(0.0, ec.floor_height * 0.5),
+                        tc.friction,
+                    ),
+                    EnvironmentDescription::get_back_wall(ec.floor_height, ec.wall_height),
+                ],
+                spawn_points: vec![],
+                finish_lines: vec![],
+            },
+            None => ec.get_floor(),
+        };
+
+        if description.spawn_points.is_empty() {
+            description
+                .spawn_points
+                .push(vec2(0.0, gc.vertical_sep * 0.15));
+        }
+
+        // Every organism in a trial starts from the same shifted spawn point
+        if gc.trials.spawn_jitter > 0.0 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let jitter = rng.gen_range(-gc.trials.spawn_jitter..gc.trials.spawn_jitter);
+            for p in description.spawn_points.iter_mut() {
+                p.x += jitter;
+            }
+        }
+
+        return description;
     }
 
     pub fn spawn(
         &mut self,
         commands: &mut Commands,
-        mesh: &Mesh2dHandle,
-        material: &Handle<ColorMaterial>,
+        meshes: &mut Assets<Mesh>,
+        handles: &Handles,
         gc: &GenerationConfig,
+        ec: &EnvironmentConfig,
+        curriculum: &Curriculum,
     ) {
-        let num_organisms = gc.num_organisms;
+        self.files_modified.clear();
+        self.descriptions = self
+            .trials
+            .clone()
+            .into_iter()
+            .map(|t| self.load_description(gc, ec, curriculum, t))
+            .collect();
+
+        // Organisms sharing a floor only need a single lane for each trial
+        self.layout = ec.layout;
+        self.lanes_per_group = match ec.layout {
+            EnvironmentLayout::Lanes => gc.num_organisms,
+            EnvironmentLayout::Shared => 1,
+        };
+        self.vertical_sep = gc.vertical_sep;
         let vertical_sep = gc.vertical_sep;
-        let width = 4000.0;
-        let height = 20.0;
+        let num_groups = self.descriptions.len();
 
-        let wall = commands
-            .spawn(Block::new(
-                vec2(-200.0, vertical_sep * num_organisms as f32 * 0.5),
-                vec2(height, vertical_sep * num_organisms as f32),
-                mesh,
-                material,
-            ))
-            .id();
-        self.env_ents.push(wall);
-
-        for i in 0..=num_organisms {
-            let floor = commands
-                .spawn(Block::new(
-                    vec2((width / 2.0) - 200.0, (i as f32) * vertical_sep),
-                    vec2(width, height),
-                    &mesh,
-                    &material,
-                ))
-                .id();
-            self.env_ents.push(floor);
+        for (g, description) in self.descriptions.iter().enumerate() {
+            // Meshes are built once and shared by every lane
+            let shape_meshes = description
+                .shapes
+                .iter()
+                .map(|s| s.shape.build_mesh().map(|m| meshes.add(m)))
+                .collect::<Vec<Option<Handle<Mesh>>>>();
+
+            // The last group also gets a floor above its top lane
+            let group_lanes = match g + 1 == num_groups {
+                true => self.lanes_per_group + 1,
+                false => self.lanes_per_group,
+            };
+            let first_lane = g * self.lanes_per_group;
+
+            for i in first_lane..(first_lane + group_lanes) {
+                let lane_offset = vec2(0.0, (i as f32) * vertical_sep);
+                for (s, mesh) in description.shapes.iter().zip(shape_meshes.iter()) {
+                    let ent = commands
+                        .spawn(EnvShape::new(s, lane_offset, mesh, handles))
+                        .id();
+                    self.env_ents.push(ent);
+                }
+            }
+
+            // Finish lines are only drawn, crossing them is checked in check_finish_lines
+            let group_height = vertical_sep * self.lanes_per_group as f32;
+            for x in description.finish_lines.iter() {
+                let line = commands
+                    .spawn(MaterialMesh2dBundle {
+                        mesh: handles.block_mesh.clone(),
+                        material: handles.finish_material.clone(),
+                        transform: Transform {
+                            translation: vec2(*x, group_height * (g as f32 + 0.5)).extend(-0.5),
+                            scale: vec2(4.0, group_height).extend(0.0),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .id();
+                self.env_ents.push(line);
+            }
         }
     }
-    pub fn despawn(&self, commands: &mut Commands) {
+
+    pub fn despawn(&mut self, commands: &mut Commands) {
         for e in self.env_ents.iter() {
             commands.entity(*e).despawn();
         }
+        self.env_ents.clear();
+    }
+
+    // Check if any environment file changed since it was loaded, a changed file that can't be
+    // loaded keeps the current environment until it's edited again
+    fn file_changed(&mut self) -> bool {
+        let mut changed = false;
+        let mut valid = true;
+        for (path, modified) in self.files_modified.iter_mut() {
+            let cur_modified = get_modified(path);
+            if cur_modified.is_none() || cur_modified == *modified {
+                continue;
+            }
+            *modified = cur_modified;
+            changed = true;
+            if let Err(err) = EnvironmentDescription::load(path) {
+                println!("Not reloading environment {:?}, {:?}", path, err);
+                valid = false;
+            }
+        }
+        return changed && valid;
     }
 }
 
+fn get_modified(path: &str) -> Option<SystemTime> {
+    return std::fs::metadata(path).ok()?.modified().ok();
+}
+
+// A shape from an environment description
 #[derive(Bundle)]
-struct Block {
+struct EnvShape {
     material_mesh_bundle: MaterialMesh2dBundle<ColorMaterial>,
     friction: Friction,
+    restitution: Restitution,
     rigid_body: RigidBody,
+    velocity: Velocity,
     collider: Collider,
+    collision_groups: CollisionGroups,
 }
-impl Block {
+impl EnvShape {
     pub fn new(
-        translation: Vec2,
-        extents: Vec2,
-        mesh: &Mesh2dHandle,
-        material: &Handle<ColorMaterial>,
+        s: &ShapeDescription,
+        lane_offset: Vec2,
+        mesh: &Option<Handle<Mesh>>,
+        handles: &Handles,
     ) -> Self {
+        // Boxes and circles scale a shared unit mesh, everything else has its own mesh
+        let (mesh, scale, collider) = match &s.shape {
+            Shape::Box { extents } => (
+                handles.block_mesh.clone(),
+                extents.extend(1.0),
+                Collider::cuboid(0.5, 0.5),
+            ),
+            Shape::Circle { radius } => (
+                handles.joint_mesh.clone(),
+                Vec3::ONE * *radius,
+                Collider::ball(1.0),
+            ),
+            Shape::Ramp { width, height } => (
+                Mesh2dHandle(mesh.clone().unwrap_or_default()),
+                Vec3::ONE,
+                Collider::triangle(Vec2::ZERO, vec2(*width, 0.0), vec2(*width, *height)),
+            ),
+            Shape::Polyline { points } => (
+                Mesh2dHandle(mesh.clone().unwrap_or_default()),
+                Vec3::ONE,
+                Collider::polyline(points.clone(), None),
+            ),
+        };
+
+        let (rigid_body, velocity) = match s.motion {
+            Some(m) => (
+                RigidBody::KinematicVelocityBased,
+                Velocity {
+                    linvel: m.linvel,
+                    angvel: m.angvel,
+                },
+            ),
+            None => (RigidBody::Fixed, Velocity::zero()),
+        };
+
         return Self {
             material_mesh_bundle: MaterialMesh2dBundle {
-                mesh: mesh.clone(),
-                material: material.clone(),
+                mesh,
+                material: handles.block_material.clone(),
                 transform: Transform {
-                    translation: translation.extend(0.0),
-                    scale: extents.extend(0.0),
-                    ..default()
+                    translation: (s.translation + lane_offset).extend(0.0),
+                    rotation: Quat::from_rotation_z(s.rotation),
+                    scale,
                 },
                 ..default()
             },
-            friction: Friction::coefficient(0.7),
-            rigid_body: RigidBody::Fixed,
-            collider: Collider::cuboid(0.5, 0.5),
+            friction: Friction::coefficient(s.friction),
+            restitution: Restitution::coefficient(s.restitution),
+            rigid_body,
+            velocity,
+            collider,
+            collision_groups: environment_collision_groups(),
         };
     }
 }
+
+// Rebuild the environment when its file changes
+pub fn hot_reload_environment(
+    mut commands: Commands,
+    mut env: ResMut<Environment>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    handles: Res<Handles>,
+    gc: Res<GenerationConfig>,
+    ec: Res<EnvironmentConfig>,
+    curriculum: Res<Curriculum>,
+    time: Res<Time>,
+    mut since_check: Local<f32>,
+) {
+    if !ec.hot_reload {
+        return;
+    }
+
+    // Only check the file once a second
+    *since_check += time.delta_seconds();
+    if *since_check < 1.0 {
+        return;
+    }
+    *since_check = 0.0;
+
+    if env.file_changed() {
+        println!("Reloading environment");
+        env.despawn(&mut commands);
+        env.spawn(&mut commands, &mut meshes, &handles, &gc, &ec, &curriculum);
+    }
+}
+
+// Record when each organism first crosses the furthest finish line of its environment
+pub fn check_finish_lines(
+    mut ol: ResMut<OrganismList>,
+    env: Res<Environment>,
+    gc: Res<GenerationConfig>,
+    joints: Query<&Transform, With<Joint>>,
+) {
+    let elapsed_secs = gc.timer.elapsed_secs();
+
+    for o in ol.organisms.iter_mut() {
+        if o.finish_time.is_some() || o.joints.is_empty() {
+            continue;
+        }
+        let finish_x = match env
+            .get_finish_lines(o.group)
+            .and_then(|f| f.iter().copied().max_by(|a, b| a.total_cmp(b)))
+        {
+            Some(x) => x,
+            None => continue,
+        };
+        let mean_x = o
+            .joints
+            .iter()
+            .filter_map(|j| joints.get(*j).ok())
+            .map(|t| t.translation.x)
+            .sum::<f32>()
+            / o.joints.len() as f32;
+        if mean_x >= finish_x {
+            o.finish_time = Some(elapsed_secs);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::{config::structs::SaveConfig, organism::organism::OrganismBuilder};
+
+// Where a builder came from
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct Lineage {
+    // Birth generation in the high bits and index in the generation in the low bits
+    pub id: u64,
+    pub parents: Vec<u64>,
+    pub birth_generation: u32,
+    // Mutations applied when the builder was made from its parent
+    pub mutations: Vec<String>,
+}
+impl Lineage {
+    pub fn get_id(generation: u32, index: usize) -> u64 {
+        return ((generation as u64) << 32) | index as u64;
+    }
+}
+
+// Give each builder of a new generation an id, the id they had before becomes their parent
+pub fn assign_ids(builders: &mut [OrganismBuilder], generation: u32) {
+    for (i, b) in builders.iter_mut().enumerate() {
+        b.lineage = Lineage {
+            id: Lineage::get_id(generation, i),
+            parents: vec![b.lineage.id],
+            birth_generation: generation,
+            mutations: std::mem::take(&mut b.lineage.mutations),
+        };
+    }
+}
+
+// Give each builder of the first generation an id with no parents
+pub fn assign_founder_ids(builders: &mut [OrganismBuilder], generation: u32) {
+    for (i, b) in builders.iter_mut().enumerate() {
+        b.lineage = Lineage {
+            id: Lineage::get_id(generation, i),
+            birth_generation: generation,
+            ..Default::default()
+        };
+    }
+}
+
+// One line of the lineage file
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LineageRecord {
+    #[serde(flatten)]
+    pub lineage: Lineage,
+    pub fitness: Option<f32>,
+}
+
+// Appends the lineage of every evaluated builder to a file in the save folder
+#[derive(Resource)]
+pub struct LineageLog {
+    start_time: String,
+}
+impl Default for LineageLog {
+    fn default() -> Self {
+        Self {
+            start_time: chrono::offset::Local::now()
+                .format("%d-%m-%Y_%H-%M")
+                .to_string(),
+        }
+    }
+}
+impl LineageLog {
+    fn get_path(&self, sc: &SaveConfig) -> PathBuf {
+        return Path::new(&sc.folder).join(format!("{}_lineage.jsonl", self.start_time));
+    }
+
+    pub fn write(&self, sc: &SaveConfig, builders: &Vec<OrganismBuilder>, scores: &Vec<f32>) {
+        if !sc.log_lineage {
+            return;
+        }
+        let path = self.get_path(sc);
+        if let Some(folder) = path.parent() {
+            if let Err(err) = fs::create_dir_all(folder) {
+                println!("Error creating lineage folder, {:?}", err);
+                return;
+            }
+        }
+        let mut file = match OpenOptions::new().create(true).append(true).open(&path) {
+            Ok(f) => f,
+            Err(err) => {
+                println!("Error opening lineage file, {:?}", err);
+                return;
+            }
+        };
+
+        let mut lines = String::new();
+        for (i, b) in builders.iter().enumerate() {
+            let record = LineageRecord {
+                lineage: b.lineage.clone(),
+                fitness: scores.get(i).copied(),
+            };
+            match serde_json::to_string(&record) {
+                Ok(json) => lines += &(json + "\n"),
+                Err(err) => println!("Error converting lineage to json, {:?}", err),
+            }
+        }
+        if let Err(err) = file.write_all(lines.as_bytes()) {
+            println!("Error writing lineage, {:?}", err);
+        }
+    }
+}
+
+// Read a lineage file and build a Graphviz graph of every ancestor of an organism
+pub fn get_ancestry_dot(lineage_path: &str, id: u64) -> Result<String, String> {
+    let text = fs::read_to_string(lineage_path).map_err(|e| e.to_string())?;
+    let mut records = HashMap::new();
+    for line in text.lines().filter(|l| !l.trim().is_empty()) {
+        let record: LineageRecord = serde_json::from_str(line).map_err(|e| e.to_string())?;
+        records.insert(record.lineage.id, record);
+    }
+    if !records.contains_key(&id) {
+        return Err(format!("Organism {} isn't in {}", id, lineage_path));
+    }
+
+    let mut dot = String::from("digraph lineage {\n    rankdir=LR;\n    node [shape=box];\n");
+    let mut queue = VecDeque::from([id]);
+    let mut visited = vec![];
+    while let Some(cur) = queue.pop_front() {
+        if visited.contains(&cur) {
+            continue;
+        }
+        visited.push(cur);
+
+        let record = match records.get(&cur) {
+            Some(r) => r,
+            None => {
+                dot += &format!("    \"{}\" [label=\"{}\\nunknown\"];\n", cur, cur);
+                continue;
+            }
+        };
+        let fitness = match record.fitness {
+            Some(f) => format!("{:.2}", f),
+            None => "-".to_string(),
+        };
+        dot += &format!(
+            "    \"{}\" [label=\"gen {} #{}\\nfitness {}\"];\n",
+            cur,
+            record.lineage.birth_generation,
+            cur & u32::MAX as u64,
+            fitness
+        );
+        for p in record.lineage.parents.iter() {
+            dot += &format!(
+                "    \"{}\" -> \"{}\" [label=\"{}\"];\n",
+                p,
+                cur,
+                record.lineage.mutations.join(", ")
+            );
+            queue.push_back(*p);
+        }
+    }
+    dot += "}\n";
+    return Ok(dot);
+}
@@ -1,41 +1,132 @@
 use bevy::{
+    ecs::system::SystemParam,
     prelude::{
-        resource_exists, App, Commands, IntoSystemConfigs, Plugin, Query, Res, ResMut, Transform,
-        Update, With,
+        resource_exists, App, Assets, Commands, IntoSystemConfigs, Mesh, Plugin, Query, Res,
+        ResMut, Startup, Transform, Update, Vec2, With,
     },
     time::Time,
 };
 
 use rand::{
     distributions::{Distribution, Uniform},
+    rngs::StdRng,
     Rng,
 };
-use std::{fs::File, io::BufReader};
 
-use self::{environment::Environment, organism_builders::get_runner_v6};
+use self::{
+    curriculum::Curriculum,
+    environment::{check_finish_lines, hot_reload_environment, Environment},
+    lineage::{assign_founder_ids, assign_ids, Lineage, LineageLog},
+    stats::{GenerationRecord, GenerationStats, Objectives, StatsLog, StatsRow},
+    trial::Trials,
+};
 use crate::{
-    config::structs::{GenerationConfig, SaveConfig},
-    controls::control_state::ControlState,
+    arena::{Arena, ArenaConfig},
+    config::structs::{EnvironmentConfig, FitnessDirection, GenerationConfig, SaveConfig},
+    controls::{control_state::ControlState, save_file::SaveFile},
     handles::Handles,
     organism::{
         joint::Joint,
+        library::Blueprint,
         organism::{Organism, OrganismBuilder},
-        organism_list::OrganismList,
+        organism_list::{OrganismList, ScoredBuilders},
+        perturbation::Perturbations,
         OrganismPlugin,
     },
-    scene_manager::is_simulation,
+    scene_manager::{is_simulation, CurrentScene, Scene},
+    task::{TaskConfig, TaskState},
 };
 
+pub mod curriculum;
+pub mod env_description;
 pub mod environment;
-mod organism_builders;
+pub mod lineage;
+pub mod stats;
+pub mod terrain;
+pub mod trial;
 
 pub struct GenerationPlugin;
 impl Plugin for GenerationPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(OrganismList::new())
             .insert_resource(Environment::new())
+            .insert_resource(Curriculum::default())
+            .insert_resource(Trials::default())
+            .insert_resource(GenerationStats::default())
+            .insert_resource(StatsLog::default())
+            .insert_resource(LineageLog::default())
             .add_plugins(OrganismPlugin)
-            .add_systems(Update, (handle_generation).run_if(is_simulation));
+            .add_systems(Startup, (resume_run, import_organism).chain())
+            .add_systems(
+                Update,
+                (
+                    handle_generation,
+                    hot_reload_environment,
+                    check_finish_lines,
+                )
+                    .run_if(is_simulation),
+            );
+    }
+}
+
+// Resources used to score organisms at the end of each trial
+#[derive(SystemParam)]
+pub struct Evaluation<'w, 's> {
+    curriculum: ResMut<'w, Curriculum>,
+    trials: ResMut<'w, Trials>,
+    stats: ResMut<'w, GenerationStats>,
+    log: ResMut<'w, StatsLog>,
+    lineage: Res<'w, LineageLog>,
+    perturbations: Res<'w, Perturbations>,
+    tc: Res<'w, TaskConfig>,
+    ts: Res<'w, TaskState>,
+    joint_transforms: Query<'w, 's, &'static Transform, With<Joint>>,
+}
+impl Evaluation<'_, '_> {
+    // Score of every spawned organism in the trial that just finished
+    fn get_trial_scores(&self, gc: &GenerationConfig, organisms: &Vec<Organism>) -> Vec<f32> {
+        let mut scores = match self.ts.get_scores(&self.tc, organisms.len()) {
+            Some(s) => s,
+            None => calc_pos_score(organisms, gc.fitness_direction, &self.joint_transforms),
+        };
+        if gc.perturbation.enable {
+            let recovery = self.perturbations.get_recovery(scores.len());
+            for (s, r) in scores.iter_mut().zip(recovery) {
+                *s += r * gc.perturbation.robustness_weight;
+            }
+        }
+        return scores;
+    }
+
+    // Row of the stats log for the generation that just finished, distance and task scores
+    // are from the last trial
+    fn get_stats_row(
+        &self,
+        gc: &GenerationConfig,
+        ol: &OrganismList,
+        scores: &Vec<f32>,
+        fitness: &Vec<f32>,
+    ) -> StatsRow {
+        let num_organisms = ol.organisms.len();
+        let objectives = Objectives {
+            distance: calc_pos_score(&ol.organisms, gc.fitness_direction, &self.joint_transforms),
+            energy: self.trials.aggregate_energy(&gc.trials),
+            task_score: self
+                .ts
+                .get_scores(&self.tc, num_organisms)
+                .unwrap_or_default(),
+            recovery: match gc.perturbation.enable {
+                true => self.perturbations.get_recovery(num_organisms),
+                false => vec![],
+            },
+        };
+        return self.log.get_row(
+            gc.cur_generation,
+            scores,
+            fitness,
+            &objectives,
+            &ol.builders,
+        );
     }
 }
 
@@ -43,11 +134,14 @@ pub fn handle_generation(
     mut commands: Commands,
     mut gc: ResMut<GenerationConfig>,
     sc: Res<SaveConfig>,
+    ec: Res<EnvironmentConfig>,
     time: Res<Time>,
     handles: Res<Handles>,
     mut ol: ResMut<OrganismList>,
+    mut env: ResMut<Environment>,
+    mut ev: Evaluation,
+    mut meshes: ResMut<Assets<Mesh>>,
     mut cs: ResMut<ControlState>,
-    joint_transforms: Query<&Transform, With<Joint>>,
 ) {
     gc.timer.tick(time.delta());
     let elapsed_secs = gc.timer.elapsed_secs();
@@ -68,97 +162,197 @@ pub fn handle_generation(
         gc.timer.reset();
         gc.timer.unpause();
         gc.unfreeze_flag = true;
+        ev.log.sim_time += gc.timer.duration().as_secs_f32();
 
-        if gc.cur_generation % sc.rate == 0 {
-            cs.save = true;
-        }
-        let new_builders = get_next_generation_builders(&mut ol, &mut gc, &joint_transforms);
+        let num_builders = ol.builders.len();
+        let trial_scores = ev.get_trial_scores(&gc, &ol.organisms);
+        let energy = ol
+            .organisms
+            .iter()
+            .map(|o| o.energy_used)
+            .collect::<Vec<f32>>();
+        ev.trials.record(&trial_scores, &energy, num_builders);
+        let num_task_stimuli = ev.tc.task.get_num_stimuli();
 
         // Despawn current generation
         ol.despawn(&mut commands);
 
+        // Run the remaining trials with the same builders before selecting
+        let next_trial = ev.trials.cur_trial + env.trials.len();
+        if next_trial < gc.trials.get_trials() {
+            ev.trials.cur_trial = next_trial;
+            env.trials = gc.trials.get_concurrent_trials(next_trial);
+            env.despawn(&mut commands);
+            env.spawn(
+                &mut commands,
+                &mut meshes,
+                &handles,
+                &gc,
+                &ec,
+                &ev.curriculum,
+            );
+            ol.spawn(&mut commands, &handles, &gc, &env, num_task_stimuli);
+            return;
+        }
+
+        let scores = ev.trials.aggregate(&gc.trials);
+        if sc.enable && gc.cur_generation % sc.rate == 0 {
+            cs.save = true;
+            ol.scored = Some(ScoredBuilders {
+                generation: gc.cur_generation,
+                builders: ol.builders.clone(),
+                scores: scores.clone(),
+            });
+        }
+        ol.best = scores
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(i, s)| (ol.builders[i].clone(), *s));
+        ev.stats.push(GenerationRecord {
+            generation: gc.cur_generation,
+            trial_scores: ev.trials.scores.clone(),
+            scores: scores.clone(),
+        });
+        let energy = ev.trials.aggregate_energy(&gc.trials);
+        let fitness = calc_fitness(&energy, gc.num_organisms, &scores);
+        let row = ev.get_stats_row(&gc, &ol, &scores, &fitness);
+        ev.log.write(&sc, &row);
+        ev.lineage.write(&sc, &ol.builders, &scores);
+        let stage_changed = ev.curriculum.update(&ec.curriculum, &fitness);
+        let new_builders = get_next_generation_builders(&ol, &mut gc, &fitness);
+        ev.trials.reset(new_builders.len());
+
+        // Generate a new course for the new generation or curriculum stage, sequential
+        // trials also need to go back to the first trial's environment
+        let first_trials = gc.trials.get_concurrent_trials(0);
+        let terrain_changed =
+            ec.terrain.enable && (ec.terrain.reseed_each_generation || stage_changed);
+        if terrain_changed || env.trials != first_trials {
+            env.trials = first_trials;
+            env.despawn(&mut commands);
+            env.spawn(
+                &mut commands,
+                &mut meshes,
+                &handles,
+                &gc,
+                &ec,
+                &ev.curriculum,
+            );
+        }
+
         // Spawn new generation
         ol.set_builders(new_builders);
-        ol.spawn(&mut commands, &handles, gc.vertical_sep);
+        ol.spawn(&mut commands, &handles, &gc, &env, num_task_stimuli);
     }
 }
 
 fn get_next_generation_builders(
-    ol: &mut OrganismList,
+    ol: &OrganismList,
     gc: &mut GenerationConfig,
-    joint_transforms: &Query<&Transform, With<Joint>>,
+    fitness: &Vec<f32>,
 ) -> Vec<OrganismBuilder> {
-    // Calculate fitness
+    // Pick the 'best' organisms
     let num_organisms = gc.num_organisms;
+    let mut rng = gc.get_rng();
+    let mut new_builders = select_builders(&ol.builders, fitness, num_organisms, &mut rng);
 
-    // Pick the 'best' organisms
-    let fitness = calc_fitness(&ol.organisms, num_organisms, joint_transforms);
+    gc.cur_generation += 1;
+    assign_ids(&mut new_builders, gc.cur_generation);
+    return new_builders;
+}
 
-    let mut rng = rand::thread_rng();
+// Pick builders weighted by their normalised fitness and mutate the picks
+pub fn select_builders(
+    builders: &[OrganismBuilder],
+    fitness: &Vec<f32>,
+    num_organisms: usize,
+    rng: &mut StdRng,
+) -> Vec<OrganismBuilder> {
     let mut new_builders = Vec::with_capacity(num_organisms);
+    if builders.is_empty() {
+        return new_builders;
+    }
 
-    while new_builders.len() <= num_organisms / 2 {
+    // Nothing would ever be picked when no organism is fit, so every organism is kept
+    let any_fit = fitness.iter().any(|f| f.is_finite() && f.abs() > 0.0);
+    if !any_fit {
+        new_builders.extend(builders.iter().take(num_organisms).cloned());
+    }
+    while any_fit && new_builders.len() <= num_organisms / 2 {
         for i in 0..num_organisms {
             let fit = fitness[i];
 
             if fit.abs() >= rng.gen::<f32>() {
-                new_builders.push(ol.builders[i].clone());
+                new_builders.push(builders[i].clone());
             }
         }
     }
 
     let sample = Uniform::from(0..new_builders.len());
     while new_builders.len() < num_organisms {
-        let index = sample.sample(&mut rng);
+        let index = sample.sample(rng);
         let new_builder = new_builders[index].clone();
         new_builders.push(new_builder);
     }
 
     // Mutate each organism
-    new_builders.iter_mut().for_each(|x| x.mutate(&mut rng));
+    new_builders.iter_mut().for_each(|x| x.mutate(rng));
 
-    gc.cur_generation += 1;
     return new_builders;
 }
 
-fn calc_fitness(
+// Distance travelled by each organism's joints
+fn calc_pos_score(
     organisms: &Vec<Organism>,
-    num_organisms: usize,
+    direction: FitnessDirection,
     joint_transforms: &Query<&Transform, With<Joint>>,
 ) -> Vec<f32> {
-    let mut fitness = Vec::with_capacity(num_organisms);
-
-    let pos_score = organisms
+    return organisms
         .iter()
         .map(|o| {
-            o.joints
+            let positions = o
+                .joints
                 .iter()
-                .map(|x| joint_transforms.get(*x).unwrap().translation.x.max(0.0))
-                .sum::<f32>()
-                / o.joints.len() as f32
+                .map(|x| joint_transforms.get(*x).unwrap().translation.truncate());
+            let num_joints = o.joints.len() as f32;
+            match direction {
+                FitnessDirection::PositiveX => {
+                    positions.map(|p| p.x.max(0.0)).sum::<f32>() / num_joints
+                }
+                FitnessDirection::PositiveXFromSpawn => {
+                    let centre = positions.sum::<Vec2>() / num_joints;
+                    (centre.x - o.start_pos.x).max(0.0)
+                }
+                FitnessDirection::Any => {
+                    let centre = positions.sum::<Vec2>() / num_joints;
+                    centre.distance(o.start_pos)
+                }
+            }
         })
         .collect::<Vec<f32>>();
-    let max_pos_score = pos_score
-        .iter()
-        .max_by(|a, b| a.abs().total_cmp(&b.abs()))
-        .unwrap();
-    let normalised_pos_score = pos_score
-        .iter()
-        .map(|x| x / max_pos_score)
-        .collect::<Vec<f32>>();
+}
 
-    let eff_score = organisms
-        .iter()
-        .map(|o| o.energy_used)
-        .collect::<Vec<f32>>();
-    let max_eff_score = eff_score
-        .iter()
-        .max_by(|a, b| a.abs().total_cmp(&b.abs()))
-        .unwrap();
-    let normalised_eff_score = pos_score
-        .iter()
-        .map(|x| x / max_eff_score)
-        .collect::<Vec<f32>>();
+// Scale scores to at most 1, every organism is equally fit if none scored
+pub fn normalise(scores: &Vec<f32>) -> Vec<f32> {
+    let max_score = scores.iter().fold(0.0, |a: f32, b| a.max(b.abs()));
+    if max_score <= 0.0 {
+        return vec![1.0; scores.len()];
+    }
+    return scores.iter().map(|s| s / max_score).collect();
+}
+
+// Energy is combined across trials like the scores, indexed by builder
+fn calc_fitness(energy: &Vec<f32>, num_organisms: usize, pos_score: &Vec<f32>) -> Vec<f32> {
+    let mut fitness = Vec::with_capacity(num_organisms);
+
+    let normalised_pos_score = normalise(pos_score);
+
+    let max_eff_score = energy.iter().fold(0.0, |a: f32, b| a.max(b.abs()));
+    let normalised_eff_score = match max_eff_score > 0.0 {
+        true => pos_score.iter().map(|x| x / max_eff_score).collect(),
+        false => normalised_pos_score.clone(),
+    };
 
     for i in 0..num_organisms {
         fitness.push(normalised_pos_score[i] * 0.5 + normalised_eff_score[i] * 0.5)
@@ -167,32 +361,140 @@ fn calc_fitness(
     return fitness;
 }
 
-pub fn setup_builders(ol: &mut OrganismList, gc: &GenerationConfig, sc: &SaveConfig) {
-    let num_organisms = gc.num_organisms;
-    let mut builders;
-
-    if sc.load_save {
-        let file = match File::open(&sc.load_file) {
-            Ok(f) => f,
-            Err(err) => {
-                println!("Error loading {:?}, {:?}", sc.load_file, err);
-                return;
-            }
-        };
-        let reader = BufReader::new(file);
-        builders = match serde_json::from_reader(reader) {
-            Ok(json) => json,
-            Err(err) => {
-                println!("Error converting json {:?}", err);
-                return;
-            }
-        };
+// Start a run with every organism copied from one builder, predators and prey start from the
+// same organism in the arena
+pub fn start_run(
+    ob: OrganismBuilder,
+    gc: &GenerationConfig,
+    ac: &ArenaConfig,
+    ol: &mut OrganismList,
+    cs: &mut CurrentScene,
+) {
+    if ac.enable {
+        ol.builders = vec![ob; ac.get_num_organisms()];
+        cs.next_scene = Scene::OrganismArena;
     } else {
-        builders = Vec::with_capacity(num_organisms);
-        for _ in 0..num_organisms {
-            builders.push(get_runner_v6());
+        ol.builders = vec![ob; gc.num_organisms];
+        cs.next_scene = Scene::OrganismSimulation;
+    }
+    assign_founder_ids(&mut ol.builders, gc.cur_generation);
+}
+
+// Load the checkpoint named in the save config and go straight to simulating it,
+// skipping the start menu and constructor
+fn resume_run(
+    mut commands: Commands,
+    sc: Res<SaveConfig>,
+    mut gc: ResMut<GenerationConfig>,
+    mut ol: ResMut<OrganismList>,
+    mut curriculum: ResMut<Curriculum>,
+    mut stats: ResMut<GenerationStats>,
+    mut arena: ResMut<Arena>,
+    mut ac: ResMut<ArenaConfig>,
+    mut cs: ResMut<CurrentScene>,
+) {
+    if !sc.load_save {
+        return;
+    }
+    let save = match SaveFile::load(&sc.load_file) {
+        Ok(s) => s,
+        Err(err) => {
+            println!("Error loading {:?}, {:?}", sc.load_file, err);
+            return;
         }
+    };
+
+    if let Some(Err(err)) = save.config.as_ref().map(|c| c.validate()) {
+        println!("Error loading {:?}, {}", sc.load_file, err);
+        return;
+    }
+    let population = save.get_population();
+    if population.is_empty() {
+        println!("Error loading {:?}, there are no organisms", sc.load_file);
+        return;
+    }
+    // Both sides of the arena need at least one organism
+    if let Some(a) = &save.arena {
+        if a.num_predators == 0 || a.num_predators >= population.len() {
+            println!(
+                "Error loading {:?}, {} predators in a population of {}",
+                sc.load_file,
+                a.num_predators,
+                population.len()
+            );
+            return;
+        }
+    }
+
+    // Run with the config the checkpoint was made with, camera and save settings are
+    // kept from the current config
+    if let Some(config) = save.config.clone() {
+        *gc = config.generation;
+        gc.reset_timer();
+        commands.insert_resource(config.environment);
+        commands.insert_resource(config.task);
+        *ac = config.arena;
+    }
+    gc.cur_generation = save.get_resume_generation();
+    gc.seed = Some(save.seed);
+    gc.unfreeze_flag = true;
+    // Selection and scoring work on the saved population, not the size in the config
+    gc.num_organisms = population.len();
+    ac.enable = save.arena.is_some();
+    if let Some(a) = &save.arena {
+        ac.num_predators = a.num_predators;
+        ac.num_prey = population.len() - a.num_predators;
     }
 
-    ol.builders = builders;
+    ol.builders = population;
+    *curriculum = save.curriculum.clone();
+    stats.history = save.stats.clone();
+    cs.next_scene = match save.arena {
+        Some(a) => {
+            arena.history = a.history;
+            Scene::OrganismArena
+        }
+        None => Scene::OrganismSimulation,
+    };
+    println!(
+        "Resuming {:?} at generation {}",
+        sc.load_file, gc.cur_generation
+    );
+}
+
+// Seed the run with an organism file, it takes the place of the last organism when a save
+// was loaded too
+fn import_organism(
+    sc: Res<SaveConfig>,
+    gc: Res<GenerationConfig>,
+    ac: Res<ArenaConfig>,
+    mut ol: ResMut<OrganismList>,
+    mut cs: ResMut<CurrentScene>,
+) {
+    let path = match &sc.import_file {
+        Some(p) => p,
+        None => return,
+    };
+    // Brains that can't drive the organism are rejected rather than replaced
+    let ob = match Blueprint::load(path).and_then(|bp| bp.try_get_builder()) {
+        Ok(ob) => ob,
+        Err(err) => {
+            println!("Error importing {:?}, {:?}", path, err);
+            return;
+        }
+    };
+
+    if ol.builders.is_empty() {
+        start_run(ob, &gc, &ac, &mut ol, &mut cs);
+        println!("Seeded the run with {:?}", path);
+        return;
+    }
+    let index = ol.builders.len() - 1;
+    ol.builders[index] = ob;
+    ol.builders[index].lineage = Lineage {
+        id: Lineage::get_id(gc.cur_generation, index),
+        birth_generation: gc.cur_generation,
+        ..Default::default()
+    };
+    println!("Imported {:?} alongside the loaded population", path);
 }
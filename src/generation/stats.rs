@@ -0,0 +1,338 @@
+use bevy::{prelude::Resource, utils::Instant};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::{config::structs::SaveConfig, organism::organism::OrganismBuilder};
+
+// Scores of a finished generation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerationRecord {
+    pub generation: u32,
+    // Score of each builder in each trial
+    pub trial_scores: Vec<Vec<f32>>,
+    // Trial scores aggregated into a single score per builder
+    pub scores: Vec<f32>,
+}
+
+#[derive(Resource, Default)]
+pub struct GenerationStats {
+    pub history: Vec<GenerationRecord>,
+}
+impl GenerationStats {
+    pub fn push(&mut self, record: GenerationRecord) {
+        self.history.push(record);
+    }
+
+    pub fn last(&self) -> Option<&GenerationRecord> {
+        return self.history.last();
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum StatsFormat {
+    Csv,
+    // One JSON object per line
+    Jsonl,
+}
+
+// Value of every objective for each organism of a generation, empty when it isn't measured
+#[derive(Default)]
+pub struct Objectives {
+    pub distance: Vec<f32>,
+    pub energy: Vec<f32>,
+    pub task_score: Vec<f32>,
+    pub recovery: Vec<f32>,
+}
+
+// Summary of a finished generation, one row of the stats log
+#[derive(Debug, Serialize)]
+pub struct StatsRow {
+    pub generation: u32,
+    pub min_score: f32,
+    pub median_score: f32,
+    pub mean_score: f32,
+    pub max_score: f32,
+    // Fitness organisms were selected by
+    pub min_fitness: f32,
+    pub median_fitness: f32,
+    pub mean_fitness: f32,
+    pub max_fitness: f32,
+    pub mean_distance: f32,
+    pub mean_energy: f32,
+    pub mean_task_score: f32,
+    pub mean_recovery: f32,
+    // Mean distance of each brain from the average brain
+    pub diversity: f32,
+    pub mean_joints: f32,
+    pub mean_bones: f32,
+    pub mean_muscles: f32,
+    pub mean_motors: f32,
+    // Seconds since the app started and seconds simulated
+    pub wall_time: f32,
+    pub sim_time: f32,
+}
+impl StatsRow {
+    const HEADER: &'static str = "generation,min_score,median_score,mean_score,max_score,\
+        min_fitness,median_fitness,mean_fitness,max_fitness,mean_distance,mean_energy,\
+        mean_task_score,mean_recovery,diversity,mean_joints,mean_bones,mean_muscles,\
+        mean_motors,wall_time,sim_time";
+
+    fn to_csv(&self) -> String {
+        return format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            self.generation,
+            self.min_score,
+            self.median_score,
+            self.mean_score,
+            self.max_score,
+            self.min_fitness,
+            self.median_fitness,
+            self.mean_fitness,
+            self.max_fitness,
+            self.mean_distance,
+            self.mean_energy,
+            self.mean_task_score,
+            self.mean_recovery,
+            self.diversity,
+            self.mean_joints,
+            self.mean_bones,
+            self.mean_muscles,
+            self.mean_motors,
+            self.wall_time,
+            self.sim_time,
+        );
+    }
+}
+
+// Appends a row to the stats file in the save folder after every generation
+#[derive(Resource)]
+pub struct StatsLog {
+    start: Instant,
+    start_time: String,
+    // Seconds simulated so far, counting every trial
+    pub sim_time: f32,
+}
+impl Default for StatsLog {
+    fn default() -> Self {
+        Self {
+            start: Instant::now(),
+            start_time: chrono::offset::Local::now()
+                .format("%d-%m-%Y_%H-%M")
+                .to_string(),
+            sim_time: 0.0,
+        }
+    }
+}
+impl StatsLog {
+    fn get_path(&self, sc: &SaveConfig) -> PathBuf {
+        let extension = match sc.stats_format {
+            StatsFormat::Csv => "csv",
+            StatsFormat::Jsonl => "jsonl",
+        };
+        return Path::new(&sc.folder).join(format!("{}_stats.{}", self.start_time, extension));
+    }
+
+    pub fn get_row(
+        &self,
+        generation: u32,
+        scores: &Vec<f32>,
+        fitness: &Vec<f32>,
+        objectives: &Objectives,
+        builders: &Vec<OrganismBuilder>,
+    ) -> StatsRow {
+        let [min_score, median_score, mean_score, max_score] = summarise(scores);
+        let [min_fitness, median_fitness, mean_fitness, max_fitness] = summarise(fitness);
+        let part_counts = builders
+            .iter()
+            .map(|b| b.get_part_counts())
+            .fold([0; 4], |a, b| {
+                [a[0] + b[0], a[1] + b[1], a[2] + b[2], a[3] + b[3]]
+            });
+        let num_builders = builders.len().max(1) as f32;
+
+        return StatsRow {
+            generation,
+            min_score,
+            median_score,
+            mean_score,
+            max_score,
+            min_fitness,
+            median_fitness,
+            mean_fitness,
+            max_fitness,
+            mean_distance: mean(&objectives.distance),
+            mean_energy: mean(&objectives.energy),
+            mean_task_score: mean(&objectives.task_score),
+            mean_recovery: mean(&objectives.recovery),
+            diversity: calc_diversity(builders),
+            mean_joints: part_counts[0] as f32 / num_builders,
+            mean_bones: part_counts[1] as f32 / num_builders,
+            mean_muscles: part_counts[2] as f32 / num_builders,
+            mean_motors: part_counts[3] as f32 / num_builders,
+            wall_time: self.start.elapsed().as_secs_f32(),
+            sim_time: self.sim_time,
+        };
+    }
+
+    // Open, append and close so each row is on disk straight away
+    pub fn write(&self, sc: &SaveConfig, row: &StatsRow) {
+        if !sc.log_stats {
+            return;
+        }
+        let path = self.get_path(sc);
+        if let Some(folder) = path.parent() {
+            if let Err(err) = std::fs::create_dir_all(folder) {
+                println!("Error creating stats folder, {:?}", err);
+                return;
+            }
+        }
+        let mut file = match OpenOptions::new().create(true).append(true).open(&path) {
+            Ok(f) => f,
+            Err(err) => {
+                println!("Error opening stats file, {:?}", err);
+                return;
+            }
+        };
+        let is_empty = file.metadata().map(|m| m.len() == 0).unwrap_or(false);
+
+        let line = match sc.stats_format {
+            StatsFormat::Csv if is_empty => format!("{}\n{}", StatsRow::HEADER, row.to_csv()),
+            StatsFormat::Csv => row.to_csv(),
+            StatsFormat::Jsonl => match serde_json::to_string(row) {
+                Ok(json) => json,
+                Err(err) => {
+                    println!("Error converting stats to json, {:?}", err);
+                    return;
+                }
+            },
+        };
+        if let Err(err) = writeln!(file, "{}", line) {
+            println!("Error writing stats, {:?}", err);
+        }
+    }
+}
+
+fn mean(values: &Vec<f32>) -> f32 {
+    return values.iter().sum::<f32>() / values.len().max(1) as f32;
+}
+
+// Min, median, mean and max, all 0 when there are no values
+fn summarise(values: &Vec<f32>) -> [f32; 4] {
+    let mut sorted = values.clone();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    return [
+        sorted.first().copied().unwrap_or(0.0),
+        sorted.get(sorted.len() / 2).copied().unwrap_or(0.0),
+        mean(values),
+        sorted.last().copied().unwrap_or(0.0),
+    ];
+}
+
+// Mean distance of each builder's brain parameters from the average, brains with fewer
+// parameters are padded with zeros
+fn calc_diversity(builders: &Vec<OrganismBuilder>) -> f32 {
+    let params = builders
+        .iter()
+        .map(|b| b.get_brain().get_params())
+        .collect::<Vec<Vec<f32>>>();
+    let num_params = params.iter().map(|p| p.len()).max().unwrap_or(0);
+
+    let mut centre = vec![0.0; num_params];
+    for p in params.iter() {
+        for (c, v) in centre.iter_mut().zip(p) {
+            *c += v / params.len() as f32;
+        }
+    }
+
+    let dists = params
+        .iter()
+        .map(|p| {
+            centre
+                .iter()
+                .enumerate()
+                .map(|(i, c)| (p.get(i).copied().unwrap_or(0.0) - c).powi(2))
+                .sum::<f32>()
+                .sqrt()
+        })
+        .collect();
+    return mean(&dists);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::vec2;
+
+    use super::*;
+    use crate::organism::bone::JointType;
+
+    fn get_builder(num_muscles: usize) -> OrganismBuilder {
+        return OrganismBuilder::new(
+            1,
+            vec![4],
+            vec![vec2(0.0, 0.0), vec2(40.0, 0.0), vec2(80.0, 20.0)],
+            vec![[0, 1], [1, 2]],
+            vec![[JointType::Revolute; 2]; 2],
+            vec![[0, 1]; num_muscles],
+            vec![],
+        );
+    }
+
+    #[test]
+    fn row_summarises_the_generation() {
+        let log = StatsLog::default();
+        let objectives = Objectives {
+            distance: vec![10.0, 20.0, 60.0],
+            energy: vec![1.0, 2.0, 3.0],
+            task_score: vec![],
+            recovery: vec![0.5, 0.5, 0.5],
+        };
+        let builders = vec![get_builder(1), get_builder(1), get_builder(4)];
+        let row = log.get_row(
+            5,
+            &vec![3.0, 1.0, 2.0],
+            &vec![0.0, 1.0, 0.5],
+            &objectives,
+            &builders,
+        );
+
+        assert_eq!(row.generation, 5);
+        assert_eq!(
+            [
+                row.min_score,
+                row.median_score,
+                row.mean_score,
+                row.max_score
+            ],
+            [1.0, 2.0, 2.0, 3.0]
+        );
+        assert_eq!(
+            [
+                row.min_fitness,
+                row.median_fitness,
+                row.mean_fitness,
+                row.max_fitness
+            ],
+            [0.0, 0.5, 0.5, 1.0]
+        );
+        assert_eq!(row.mean_distance, 30.0);
+        assert_eq!(row.mean_energy, 2.0);
+        assert_eq!(row.mean_task_score, 0.0);
+        assert_eq!(row.mean_recovery, 0.5);
+        assert_eq!([row.mean_joints, row.mean_bones], [3.0, 2.0]);
+        assert_eq!(row.mean_muscles, 2.0);
+        assert_eq!(
+            row.to_csv().split(',').count(),
+            StatsRow::HEADER.split(',').count()
+        );
+    }
+
+    #[test]
+    fn empty_generation_has_zero_row() {
+        let row = StatsLog::default().get_row(0, &vec![], &vec![], &Objectives::default(), &vec![]);
+        assert_eq!([row.min_score, row.max_fitness, row.mean_energy], [0.0; 3]);
+    }
+}
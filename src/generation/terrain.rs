@@ -0,0 +1,151 @@
+use bevy::{
+    math::vec2,
+    prelude::{Mesh, Vec2},
+    render::{mesh::Indices, render_resource::PrimitiveTopology},
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct TerrainConfig {
+    // Use generated terrain instead of flat floors
+    pub enable: bool,
+    pub seed: u64,
+    // Generate a new course every generation, still shared by the whole generation
+    pub reseed_each_generation: bool,
+    pub length: f32,
+    pub segment_len: f32,
+    // Max random height change between segments
+    pub roughness: f32,
+    // Incline of the course in radians
+    pub slope: f32,
+    pub step_chance: f32,
+    pub step_height: f32,
+    pub gap_chance: f32,
+    pub gap_width: f32,
+    pub gap_depth: f32,
+    pub friction: f32,
+}
+impl Default for TerrainConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            seed: 0,
+            reseed_each_generation: false,
+            length: 4000.0,
+            segment_len: 40.0,
+            roughness: 5.0,
+            slope: 0.0,
+            step_chance: 0.0,
+            step_height: 10.0,
+            gap_chance: 0.0,
+            gap_width: 40.0,
+            gap_depth: 40.0,
+            friction: 0.7,
+        }
+    }
+}
+impl TerrainConfig {
+    // Seed used for a generation so every organism sees the same course
+    pub fn get_seed(&self, generation: u32) -> u64 {
+        return match self.reseed_each_generation {
+            true => self.seed.wrapping_add(generation as u64),
+            false => self.seed,
+        };
+    }
+}
+
+// Generate the ground as a polyline starting at the back wall
+pub fn generate_terrain(tc: &TerrainConfig, seed: u64) -> Vec<Vec2> {
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    // Keep the spawn area flat so organisms start on level ground
+    let start_x = -200.0;
+    let flat_len = 400.0;
+    let mut points = vec![vec2(start_x, 0.0), vec2(start_x + flat_len, 0.0)];
+
+    let mut x = start_x + flat_len;
+    let mut y = 0.0;
+    let end_x = start_x + tc.length;
+    let seg = tc.segment_len.max(1.0);
+    let slope = tc.slope.tan();
+
+    while x < end_x {
+        if rng.gen::<f32>() < tc.gap_chance {
+            // Gaps are trenches so organisms that fall in stay in their lane
+            // Always move forward so a gap width of zero can't stall the loop
+            points.push(vec2(x, y - tc.gap_depth));
+            x += tc.gap_width.max(seg);
+            points.push(vec2(x, y - tc.gap_depth));
+            points.push(vec2(x, y));
+            continue;
+        }
+        if rng.gen::<f32>() < tc.step_chance {
+            y += tc.step_height;
+            points.push(vec2(x, y));
+        }
+
+        x += seg;
+        y += seg * slope;
+        if tc.roughness > 0.0 {
+            y += rng.gen_range(-tc.roughness..tc.roughness);
+        }
+        points.push(vec2(x, y));
+    }
+
+    return points;
+}
+
+// Build a filled mesh under the polyline so the ground can be drawn
+pub fn build_terrain_mesh(points: &Vec<Vec2>) -> Mesh {
+    let base_depth = 20.0;
+    let bottom = points
+        .iter()
+        .map(|p| p.y)
+        .min_by(|a, b| a.total_cmp(b))
+        .unwrap_or(0.0)
+        - base_depth;
+
+    let mut positions = Vec::with_capacity(points.len() * 2);
+    for p in points.iter() {
+        positions.push([p.x, p.y, 0.0]);
+        positions.push([p.x, bottom, 0.0]);
+    }
+
+    let mut indices = Vec::with_capacity(points.len() * 6);
+    for i in 0..(points.len().saturating_sub(1) as u32) {
+        let top_a = i * 2;
+        let bottom_a = top_a + 1;
+        let top_b = top_a + 2;
+        let bottom_b = top_a + 3;
+        indices.extend([bottom_a, bottom_b, top_b, bottom_a, top_b, top_a]);
+    }
+
+    let normals = vec![[0.0, 0.0, 1.0]; positions.len()];
+    let uvs = vec![[0.0, 0.0]; positions.len()];
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    return mesh;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_gaps_still_ends() {
+        let tc = TerrainConfig {
+            gap_chance: 1.0,
+            gap_width: 0.0,
+            ..Default::default()
+        };
+        let points = generate_terrain(&tc, 0);
+        assert!(points.last().unwrap().x >= -200.0 + tc.length);
+        assert!(points.windows(2).all(|p| p[1].x >= p[0].x));
+    }
+}
@@ -0,0 +1,126 @@
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum TrialMode {
+    // Trials run one after another, each taking a full generation duration
+    Sequential,
+    // Every trial runs at the same time in its own set of lanes
+    Parallel,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum TrialAggregate {
+    Mean,
+    Min,
+    // Percentile between 0 and 100
+    Percentile(f32),
+}
+impl TrialAggregate {
+    pub fn aggregate(&self, scores: &Vec<f32>) -> f32 {
+        if scores.is_empty() {
+            return 0.0;
+        }
+        return match self {
+            TrialAggregate::Mean => scores.iter().sum::<f32>() / scores.len() as f32,
+            TrialAggregate::Min => scores.iter().copied().fold(f32::INFINITY, f32::min),
+            TrialAggregate::Percentile(p) => {
+                let mut sorted = scores.clone();
+                sorted.sort_by(|a, b| a.total_cmp(b));
+                let index = ((p / 100.0).clamp(0.0, 1.0) * (sorted.len() - 1) as f32).round();
+                sorted[index as usize]
+            }
+        };
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct TrialConfig {
+    // Number of trials each organism is evaluated over
+    pub trials: usize,
+    pub mode: TrialMode,
+    pub aggregate: TrialAggregate,
+    // Offset the terrain seed by the trial number
+    pub vary_seed: bool,
+    // Environment files cycled through by trial, the environment config's file is used when empty
+    pub files: Vec<String>,
+    // Max random horizontal offset of each trial's spawn point
+    pub spawn_jitter: f32,
+}
+impl Default for TrialConfig {
+    fn default() -> Self {
+        Self {
+            trials: 1,
+            mode: TrialMode::Sequential,
+            aggregate: TrialAggregate::Mean,
+            vary_seed: true,
+            files: vec![],
+            spawn_jitter: 0.0,
+        }
+    }
+}
+impl TrialConfig {
+    pub fn get_trials(&self) -> usize {
+        return self.trials.max(1);
+    }
+
+    // Trials that are spawned at the same time
+    pub fn get_concurrent_trials(&self, cur_trial: usize) -> Vec<usize> {
+        return match self.mode {
+            TrialMode::Sequential => vec![cur_trial],
+            TrialMode::Parallel => (0..self.get_trials()).collect(),
+        };
+    }
+
+    pub fn get_file(&self, trial: usize) -> Option<&String> {
+        if self.files.is_empty() {
+            return None;
+        }
+        return Some(&self.files[trial % self.files.len()]);
+    }
+}
+
+// Scores and energy used of the current generation, indexed by builder then trial
+#[derive(Resource, Default)]
+pub struct Trials {
+    pub cur_trial: usize,
+    pub scores: Vec<Vec<f32>>,
+    pub energy: Vec<Vec<f32>>,
+}
+impl Trials {
+    pub fn reset(&mut self, num_builders: usize) {
+        self.cur_trial = 0;
+        self.scores = vec![vec![]; num_builders];
+        self.energy = vec![vec![]; num_builders];
+    }
+
+    // Record the scores of every spawned organism, organisms are grouped by trial
+    pub fn record(&mut self, pos_score: &Vec<f32>, energy: &Vec<f32>, num_builders: usize) {
+        if self.scores.len() != num_builders {
+            self.scores = vec![vec![]; num_builders];
+            self.energy = vec![vec![]; num_builders];
+        }
+        for (i, (score, used)) in pos_score.iter().zip(energy.iter()).enumerate() {
+            self.scores[i % num_builders].push(*score);
+            self.energy[i % num_builders].push(*used);
+        }
+    }
+
+    pub fn aggregate(&self, tc: &TrialConfig) -> Vec<f32> {
+        return self
+            .scores
+            .iter()
+            .map(|s| tc.aggregate.aggregate(s))
+            .collect();
+    }
+
+    // Energy used by each builder, combined across trials the same way as the scores
+    pub fn aggregate_energy(&self, tc: &TrialConfig) -> Vec<f32> {
+        return self
+            .energy
+            .iter()
+            .map(|e| tc.aggregate.aggregate(e))
+            .collect();
+    }
+}
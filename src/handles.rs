@@ -4,6 +4,10 @@ use bevy::{
     sprite::{ColorMaterial, Mesh2dHandle},
 };
 
+use crate::color_palette::{
+    BONE, ENVIRONMENT, JOINT, MUSCLE_CONTRACT, MUSCLE_EXPAND, MUSCLE_NEUTRAL,
+};
+
 #[derive(Resource)]
 pub struct Handles {
     pub block_mesh: Mesh2dHandle,
@@ -16,6 +20,25 @@ pub struct Handles {
     pub muscle_contract_material: Handle<ColorMaterial>,
     pub muscle_expand_material: Handle<ColorMaterial>,
     pub muscle_neutral_material: Handle<ColorMaterial>,
+    pub motor_material: Handle<ColorMaterial>,
+    pub finish_material: Handle<ColorMaterial>,
+    pub target_material: Handle<ColorMaterial>,
+}
+
+// Change the alpha of every material organisms are drawn with
+pub fn set_organism_alpha(handles: &Handles, materials: &mut Assets<ColorMaterial>, alpha: f32) {
+    for h in [
+        &handles.joint_material,
+        &handles.bone_material,
+        &handles.muscle_contract_material,
+        &handles.muscle_expand_material,
+        &handles.muscle_neutral_material,
+        &handles.motor_material,
+    ] {
+        if let Some(m) = materials.get_mut(h) {
+            m.color.set_a(alpha);
+        }
+    }
 }
 
 pub fn setup_handles(
@@ -25,14 +48,17 @@ pub fn setup_handles(
 ) {
     commands.insert_resource(Handles {
         block_mesh: meshes.add(shape::Quad::new(Vec2::ONE).into()).into(),
-        block_material: materials.add(ColorMaterial::from(Color::BLACK)),
+        block_material: materials.add(ColorMaterial::from(ENVIRONMENT)),
         joint_mesh: meshes.add(shape::Circle::new(1.0).into()).into(),
-        joint_material: materials.add(ColorMaterial::from(Color::hsl(115.0, 0.60, 0.35))),
+        joint_material: materials.add(ColorMaterial::from(JOINT)),
         bone_mesh: meshes.add(shape::Quad::new(vec2(1.0, 1.0)).into()).into(),
-        bone_material: materials.add(ColorMaterial::from(Color::hsl(0.0, 0.50, 0.90))),
+        bone_material: materials.add(ColorMaterial::from(BONE)),
         muscle_mesh: meshes.add(shape::Quad::new(vec2(1.0, 1.0)).into()).into(),
-        muscle_contract_material: materials.add(ColorMaterial::from(Color::hsl(0.0, 0.60, 0.45))),
-        muscle_expand_material: materials.add(ColorMaterial::from(Color::hsl(240.0, 0.60, 0.45))),
-        muscle_neutral_material: materials.add(ColorMaterial::from(Color::hsl(300.0, 0.60, 0.45))),
+        muscle_contract_material: materials.add(ColorMaterial::from(MUSCLE_CONTRACT)),
+        muscle_expand_material: materials.add(ColorMaterial::from(MUSCLE_EXPAND)),
+        muscle_neutral_material: materials.add(ColorMaterial::from(MUSCLE_NEUTRAL)),
+        motor_material: materials.add(ColorMaterial::from(Color::hsl(30.0, 0.80, 0.50))),
+        finish_material: materials.add(ColorMaterial::from(Color::hsla(60.0, 0.80, 0.50, 0.5))),
+        target_material: materials.add(ColorMaterial::from(Color::hsla(170.0, 0.70, 0.45, 0.6))),
     });
 }
@@ -1,22 +1,29 @@
 extern crate console_error_panic_hook;
 
+use arena::ArenaPlugin;
 use bevy::prelude::*;
 use bevy::{
     diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
     window::WindowMode,
 };
 use bevy_rapier2d::prelude::*;
+use collider_layer::ColliderLayerPlugin;
 use handles::setup_handles;
 use organism_constructor::OrganismConstructionPlugin;
+use replay::ReplayPlugin;
 use scene_manager::SceneManagerPlugin;
+use start_menu::StartMenuPlugin;
 use std::env;
 use std::panic;
+use task::TaskPlugin;
 
 use controls::ControlPlugin;
 use generation::GenerationPlugin;
 
 use crate::config::ConfigPlugin;
 
+mod arena;
+mod cli;
 mod collider_layer;
 mod color_palette;
 mod config;
@@ -25,11 +32,19 @@ mod generation;
 mod handles;
 mod organism;
 mod organism_constructor;
+mod replay;
 mod scene_manager;
+mod start_menu;
+mod task;
 
 fn main() {
     panic::set_hook(Box::new(console_error_panic_hook::hook));
 
+    let args = env::args().collect::<Vec<String>>();
+    if cli::run(&args) {
+        return;
+    }
+
     let profiling_mode = false;
     let debug_mode = false;
 
@@ -63,13 +78,16 @@ fn main() {
     )
     .add_systems(PreStartup, setup_handles)
     .add_plugins((
-        RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0),
-        // RapierPhysicsPlugin::<ColliderLayerHook>::pixels_per_meter(100.0),
+        ColliderLayerPlugin,
         ConfigPlugin,
         ControlPlugin,
         SceneManagerPlugin,
         GenerationPlugin,
         OrganismConstructionPlugin,
+        TaskPlugin,
+        StartMenuPlugin,
+        ArenaPlugin,
+        ReplayPlugin,
     ));
 
     if profiling_mode {
@@ -7,9 +7,84 @@ use bevy::{
     sprite::{ColorMaterial, MaterialMesh2dBundle, Mesh2dHandle},
 };
 use bevy_rapier2d::prelude::{
-    AdditionalMassProperties, Collider, ColliderMassProperties, ExternalImpulse, ImpulseJoint,
-    RevoluteJointBuilder, RigidBody,
+    AdditionalMassProperties, Collider, ColliderMassProperties, ExternalForce, ExternalImpulse,
+    FixedJointBuilder, ImpulseJoint, PrismaticJointBuilder, RevoluteJointBuilder, RigidBody,
+    Velocity,
 };
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+use crate::collider_layer::{OrganismCollision, OrganismCollisionBundle};
+
+// How a bone end connects to a joint
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum JointType {
+    // Free bearing
+    Revolute,
+    // Bearing limited to an angle range (radians) relative to the spawn pose
+    LimitedRevolute { min: f32, max: f32 },
+    // Welded
+    Fixed,
+    // Slides along the bone within a distance range
+    Prismatic { min: f32, max: f32 },
+}
+impl Default for JointType {
+    fn default() -> Self {
+        return JointType::Revolute;
+    }
+}
+impl JointType {
+    // Create the rapier joint connecting a bone to one of its joints
+    // anchor is the offset from the bone's centre to the joint
+    fn build(&self, bone_ent: Entity, anchor: Vec2) -> ImpulseJoint {
+        return match *self {
+            JointType::Revolute => ImpulseJoint::new(
+                bone_ent,
+                RevoluteJointBuilder::new().local_anchor1(anchor).build(),
+            ),
+            JointType::LimitedRevolute { min, max } => ImpulseJoint::new(
+                bone_ent,
+                RevoluteJointBuilder::new()
+                    .local_anchor1(anchor)
+                    .limits([min, max])
+                    .build(),
+            ),
+            JointType::Fixed => ImpulseJoint::new(
+                bone_ent,
+                FixedJointBuilder::new().local_anchor1(anchor).build(),
+            ),
+            JointType::Prismatic { min, max } => ImpulseJoint::new(
+                bone_ent,
+                PrismaticJointBuilder::new(anchor.normalize_or_zero())
+                    .local_anchor1(anchor)
+                    .limits([min, max])
+                    .build(),
+            ),
+        };
+    }
+
+    // Mutate the limits of limited joint types
+    pub fn mutate(&mut self, rng: &mut impl Rng, mr: f32, mf: f32) {
+        match self {
+            JointType::LimitedRevolute { min, max } | JointType::Prismatic { min, max } => {
+                if rng.gen::<f32>() <= mr {
+                    *min += rng.gen_range(-mf..mf);
+                    *max += rng.gen_range(-mf..mf);
+                    if *min > *max {
+                        std::mem::swap(min, max);
+                    }
+                }
+            }
+            JointType::Revolute | JointType::Fixed => {}
+        }
+        // Angles past a half turn either way aren't valid revolute limits
+        if let JointType::LimitedRevolute { min, max } = self {
+            *min = min.clamp(-PI, PI);
+            *max = max.clamp(-PI, PI);
+        }
+    }
+}
 
 #[derive(Bundle)]
 pub struct BoneBundle {
@@ -17,7 +92,10 @@ pub struct BoneBundle {
     spatial_bundle: SpatialBundle,
     rigid_body: RigidBody,
     external_impulse: ExternalImpulse,
+    external_force: ExternalForce,
+    velocity: Velocity,
     mass: AdditionalMassProperties,
+    axis: BoneAxis,
 }
 impl BoneBundle {
     pub fn spawn(
@@ -26,6 +104,8 @@ impl BoneBundle {
         material: &Handle<ColorMaterial>,
         joints: [Entity; 2],
         joint_pos: [Vec2; 2],
+        connections: [JointType; 2],
+        collision: &OrganismCollision,
     ) -> (Entity, Vec2) {
         let width = 3.0;
 
@@ -45,22 +125,21 @@ impl BoneBundle {
                 material,
                 vec2(width, len - 10.0),
                 z_rot,
+                collision,
             ))
             .id();
-        let bone_ent = commands.spawn(BoneBundle::new(mid, 0.0)).id();
+        let bone_ent = commands.spawn(BoneBundle::new(mid, 0.0, dir)).id();
         commands.get_entity(bone_ent).unwrap().add_child(display);
 
-        let bearing_a = RevoluteJointBuilder::new().local_anchor1(-dir).build();
-        let bearing_b = RevoluteJointBuilder::new().local_anchor1(dir).build();
-        let axel_a = commands.spawn(ImpulseJoint::new(bone_ent, bearing_a)).id();
-        let axel_b = commands.spawn(ImpulseJoint::new(bone_ent, bearing_b)).id();
+        let axel_a = commands.spawn(connections[0].build(bone_ent, -dir)).id();
+        let axel_b = commands.spawn(connections[1].build(bone_ent, dir)).id();
 
         commands.get_entity(joints[0]).unwrap().add_child(axel_a);
         commands.get_entity(joints[1]).unwrap().add_child(axel_b);
         return (bone_ent, mid);
     }
 
-    pub fn new(translation: Vec2, z_rot: f32) -> Self {
+    pub fn new(translation: Vec2, z_rot: f32, half_axis: Vec2) -> Self {
         return Self {
             bone: Bone,
             spatial_bundle: SpatialBundle::from_transform(Transform {
@@ -70,7 +149,10 @@ impl BoneBundle {
             }),
             rigid_body: RigidBody::Dynamic,
             external_impulse: ExternalImpulse::default(),
+            external_force: ExternalForce::default(),
+            velocity: Velocity::default(),
             mass: AdditionalMassProperties::Mass(1.0),
+            axis: BoneAxis(half_axis),
         };
     }
 }
@@ -79,6 +161,7 @@ impl BoneBundle {
 pub struct BoneDisplayBundle {
     material_mesh_bundle: MaterialMesh2dBundle<ColorMaterial>,
     collider: Collider,
+    collision: OrganismCollisionBundle,
     // sensor: Sensor,
     collider_mass: ColliderMassProperties,
 }
@@ -88,6 +171,7 @@ impl BoneDisplayBundle {
         material: &Handle<ColorMaterial>,
         size: Vec2,
         z_rot: f32,
+        collision: &OrganismCollision,
     ) -> Self {
         return Self {
             material_mesh_bundle: MaterialMesh2dBundle {
@@ -102,6 +186,7 @@ impl BoneDisplayBundle {
             },
             collider_mass: ColliderMassProperties::Density(0.2),
             collider: Collider::cuboid(0.5, 0.5),
+            collision: collision.get_bundle(),
             // sensor: Sensor,
         };
     }
@@ -111,3 +196,32 @@ impl BoneDisplayBundle {
 // Used for muscles to pull on
 #[derive(Component)]
 pub struct Bone;
+
+// Vector from the centre of a bone to its second joint in the bone's local space
+#[derive(Component)]
+pub struct BoneAxis(pub Vec2);
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn revolute_limits_stay_in_range() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut joint_type = JointType::LimitedRevolute {
+            min: -3.0,
+            max: 3.0,
+        };
+        for _ in 0..1000 {
+            joint_type.mutate(&mut rng, 1.0, 2.0);
+            match joint_type {
+                JointType::LimitedRevolute { min, max } => {
+                    assert!(-PI <= min && min <= max && max <= PI);
+                }
+                _ => panic!("mutating changed the joint type"),
+            }
+        }
+    }
+}
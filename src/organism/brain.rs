@@ -1,7 +1,8 @@
 use core::panic;
 
+use bevy::asset::Error;
 use nalgebra::DMatrix;
-use rand::{rngs::ThreadRng, Rng};
+use rand::{rngs::StdRng, Rng};
 use serde::{de::Visitor, ser::SerializeSeq, Deserialize, Deserializer, Serialize};
 
 pub type Matrix = DMatrix<f32>;
@@ -15,8 +16,14 @@ impl Serialize for MxNMatrix {
         S: serde::Serializer,
     {
         let m = &self.0;
+        // Binary formats get the shape as integers and the data in one block
+        if !serializer.is_human_readable() {
+            let data = m.as_slice();
+            return (m.nrows() as u32, m.ncols() as u32, data).serialize(serializer);
+        }
+
         // Allocate space for sequence
-        let mut m_seq = serializer.serialize_seq(Some(m.iter().len()))?;
+        let mut m_seq = serializer.serialize_seq(Some(m.len() + 2))?;
 
         // Add matrix shape data to sequence
         m_seq.serialize_element(&m.shape().0)?;
@@ -34,6 +41,19 @@ impl<'de> Deserialize<'de> for MxNMatrix {
     where
         D: Deserializer<'de>,
     {
+        if !d.is_human_readable() {
+            let (rows, cols, data) = <(u32, u32, Vec<f32>)>::deserialize(d)?;
+            let (rows, cols) = (rows as usize, cols as usize);
+            if data.len() != rows * cols {
+                return Err(serde::de::Error::custom(format!(
+                    "{}x{} matrix has {} values",
+                    rows,
+                    cols,
+                    data.len()
+                )));
+            }
+            return Ok(MxNMatrix(Matrix::from_vec(rows, cols, data)));
+        }
         let m = d.deserialize_seq(MxMMatrixVisitor)?;
         return Ok(m);
     }
@@ -102,6 +122,68 @@ impl Brain {
         return self.weights[0].0.shape().0;
     }
 
+    pub fn get_num_outputs(&self) -> usize {
+        return self.memory.len();
+    }
+
+    // Sizes of the layers between the inputs and outputs
+    pub fn get_hidden_layers(&self) -> Vec<usize> {
+        let num_layers = self.weights.len().saturating_sub(1);
+        return self.weights[..num_layers]
+            .iter()
+            .map(|w| w.0.ncols())
+            .collect();
+    }
+
+    // Every layer has to take the outputs of the layer before it, brains loaded from files
+    // are checked before they're used
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.weights.is_empty() || self.weights.len() != self.biases.len() {
+            return Err(Error::msg("Brain needs weights and biases for every layer"));
+        }
+        for (i, (w, b)) in self.weights.iter().zip(self.biases.iter()).enumerate() {
+            let num_outputs = w.0.ncols();
+            if b.0.shape() != (1, num_outputs) {
+                return Err(Error::msg(format!(
+                    "Brain layer {} has {} outputs but {} biases",
+                    i,
+                    num_outputs,
+                    b.0.len()
+                )));
+            }
+            if let Some(next) = self.weights.get(i + 1) {
+                if next.0.nrows() != num_outputs {
+                    return Err(Error::msg(format!(
+                        "Brain layer {} has {} outputs but layer {} takes {} inputs",
+                        i,
+                        num_outputs,
+                        i + 1,
+                        next.0.nrows()
+                    )));
+                }
+            }
+        }
+        let num_outputs = self.weights[self.weights.len() - 1].0.ncols();
+        if self.memory.len() != num_outputs {
+            return Err(Error::msg(format!(
+                "Brain has {} outputs but remembers {}",
+                num_outputs,
+                self.memory.len()
+            )));
+        }
+        return Ok(());
+    }
+
+    // Every weight then every bias
+    pub fn get_params(&self) -> Vec<f32> {
+        return self
+            .weights
+            .iter()
+            .chain(self.biases.iter())
+            .flat_map(|m| m.0.iter().copied())
+            .collect();
+    }
+
     pub fn add_io(&mut self) {
         println!("adding io");
         debug_matrix_shapes(&self.weights, &"weights");
@@ -152,6 +234,16 @@ impl Brain {
         debug_matrix_shapes(&self.biases, &"biases");
     }
 
+    // Add or remove inputs from the end of the input layer, new inputs start with no influence
+    pub fn set_num_inputs(&mut self, num_inputs: usize) {
+        while self.get_num_inputs() < num_inputs {
+            insert_row(&mut self.weights[0]);
+        }
+        while self.get_num_inputs() > num_inputs {
+            remove_row(&mut self.weights[0]);
+        }
+    }
+
     // Set the memory used for feed forward
     pub fn set_memory(&mut self, remember: Vec<f32>) {
         if self.memory.len() != remember.len() {
@@ -191,7 +283,7 @@ impl Brain {
     }
 
     // Mutate brain based on learning rate and learning factor
-    pub fn learn(&mut self, rng: &mut ThreadRng, learning_rate: f32, learning_factor: f32) {
+    pub fn learn(&mut self, rng: &mut StdRng, learning_rate: f32, learning_factor: f32) {
         for weight in self.weights.iter_mut() {
             Self::mutate_matrix(rng, weight, learning_rate, learning_factor);
         }
@@ -201,7 +293,7 @@ impl Brain {
         }
     }
 
-    fn mutate_matrix(rng: &mut ThreadRng, m: &mut MxNMatrix, mut_rate: f32, mut_factor: f32) {
+    fn mutate_matrix(rng: &mut StdRng, m: &mut MxNMatrix, mut_rate: f32, mut_factor: f32) {
         for cell in m.0.iter_mut() {
             if rng.gen::<f32>() <= mut_rate {
                 *cell += (rng.gen::<f32>() - 0.5) * mut_factor;
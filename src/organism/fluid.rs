@@ -0,0 +1,96 @@
+use bevy::{
+    math::vec2,
+    prelude::{Query, Res, ResMut, Transform, Vec2, With},
+};
+use bevy_rapier2d::prelude::{ExternalForce, RapierConfiguration, Velocity};
+use serde::{Deserialize, Serialize};
+
+use crate::config::structs::EnvironmentConfig;
+
+use super::bone::{Bone, BoneAxis};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum MediumType {
+    // Normal gravity, no drag
+    Air,
+    // Bones are pushed around by drag and lift
+    Fluid,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct MediumConfig {
+    pub medium: MediumType,
+    // Multiplier applied to gravity in a fluid
+    pub gravity_scale: f32,
+    pub density: f32,
+    // Drag against motion perpendicular to a bone
+    pub normal_drag: f32,
+    // Drag against motion along a bone
+    pub tangential_drag: f32,
+    pub lift: f32,
+    // Velocity of the fluid
+    pub current: Vec2,
+}
+impl Default for MediumConfig {
+    fn default() -> Self {
+        Self {
+            medium: MediumType::Air,
+            gravity_scale: 0.0,
+            density: 0.001,
+            normal_drag: 1.2,
+            tangential_drag: 0.05,
+            lift: 0.5,
+            current: Vec2::ZERO,
+        }
+    }
+}
+
+// Scale gravity once at startup when organisms are in a fluid
+pub fn setup_medium_gravity(ec: Res<EnvironmentConfig>, mut rc: ResMut<RapierConfiguration>) {
+    if ec.medium.medium == MediumType::Fluid {
+        rc.gravity *= ec.medium.gravity_scale;
+    }
+}
+
+// Apply drag and lift to each bone based on its orientation relative to the flow,
+// bones are treated as flat plates
+pub fn apply_fluid_forces(
+    ec: Res<EnvironmentConfig>,
+    mut bones: Query<(&Transform, &Velocity, &BoneAxis, &mut ExternalForce), With<Bone>>,
+) {
+    let mc = &ec.medium;
+    if mc.medium != MediumType::Fluid {
+        return;
+    }
+
+    for (transform, velocity, axis, mut force) in bones.iter_mut() {
+        let half_axis = (transform.rotation * axis.0.extend(0.0)).truncate();
+        let len = half_axis.length() * 2.0;
+        let tangent = half_axis.normalize_or_zero();
+        let normal = vec2(-tangent.y, tangent.x);
+
+        // Velocity relative to the fluid
+        let rel_vel = velocity.linvel - mc.current;
+        let speed = rel_vel.length();
+        if speed <= f32::EPSILON {
+            force.force = Vec2::ZERO;
+            continue;
+        }
+
+        let v_n = rel_vel.dot(normal);
+        let v_t = rel_vel.dot(tangent);
+        let q = 0.5 * mc.density * len;
+
+        let drag = -normal * (q * mc.normal_drag * v_n * v_n.abs())
+            - tangent * (q * mc.tangential_drag * v_t * v_t.abs());
+
+        // Lift acts perpendicular to the flow and peaks when the bone is at 45 degrees to it
+        let flow_dir = rel_vel / speed;
+        let sin_2_aoa = 2.0 * flow_dir.perp_dot(tangent) * flow_dir.dot(tangent);
+        let lift_dir = vec2(-flow_dir.y, flow_dir.x);
+        let lift = lift_dir * (q * mc.lift * speed * speed * sin_2_aoa);
+
+        force.force = drag + lift;
+    }
+}
@@ -1,8 +1,9 @@
-use rand::{rngs::ThreadRng, Rng};
+use rand::{rngs::StdRng, Rng};
 use serde::{Deserialize, Serialize};
 
 // Stores the genetic info of the creature
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Genome {
     pub genome_mr: Allele,
     pub genome_mf: Allele,
@@ -13,10 +14,14 @@ pub struct Genome {
     pub bone_mr: Allele,
     pub bone_mf: Allele,
     pub muscle_mr: Allele,
+    pub muscle_param_mr: Allele,
+    pub muscle_param_mf: Allele,
+    pub joint_limit_mr: Allele,
+    pub joint_limit_mf: Allele,
     pub internal_clock: Allele,
 }
 impl Genome {
-    pub fn mutate(&mut self, rng: &mut ThreadRng) {
+    pub fn mutate(&mut self, rng: &mut StdRng) {
         let mr = self.genome_mr.val;
         let mf = self.genome_mf.val;
 
@@ -29,6 +34,10 @@ impl Genome {
         self.bone_mr.mutate_meta(rng, mr, mf);
         self.bone_mf.mutate_meta(rng, mr, mf);
         self.muscle_mr.mutate_meta(rng, mr, mf);
+        self.muscle_param_mr.mutate_meta(rng, mr, mf);
+        self.muscle_param_mf.mutate_meta(rng, mr, mf);
+        self.joint_limit_mr.mutate_meta(rng, mr, mf);
+        self.joint_limit_mf.mutate_meta(rng, mr, mf);
         self.internal_clock.mutate_meta(rng, mr, mf);
 
         self.genome_mr.mutate_val(rng);
@@ -40,6 +49,10 @@ impl Genome {
         self.bone_mr.mutate_val(rng);
         self.bone_mf.mutate_val(rng);
         self.muscle_mr.mutate_val(rng);
+        self.muscle_param_mr.mutate_val(rng);
+        self.muscle_param_mf.mutate_val(rng);
+        self.joint_limit_mr.mutate_val(rng);
+        self.joint_limit_mf.mutate_val(rng);
         self.internal_clock.mutate_val(rng);
     }
 }
@@ -55,6 +68,10 @@ impl Default for Genome {
             bone_mr: Allele::new(0.01, 0.2, 0.002),
             bone_mf: Allele::new(10.0, 0.2, 0.002),
             muscle_mr: Allele::new(0.01, 0.2, 1.0),
+            muscle_param_mr: Allele::new(0.05, 0.02, 0.02),
+            muscle_param_mf: Allele::new(0.1, 0.02, 0.02),
+            joint_limit_mr: Allele::new(0.05, 0.02, 0.02),
+            joint_limit_mf: Allele::new(0.05, 0.02, 0.02),
             internal_clock: Allele::new(20.0, 0.002, 0.002),
         }
     }
@@ -77,7 +94,7 @@ impl Allele {
     }
 
     // mutate allele meta data
-    pub fn mutate_meta(&mut self, rng: &mut ThreadRng, mr: f32, mf: f32) {
+    pub fn mutate_meta(&mut self, rng: &mut StdRng, mr: f32, mf: f32) {
         if rng.gen::<f32>() <= mr {
             let r = -mf..mf;
             self.mutate_rate += rng.gen_range(r.clone());
@@ -89,7 +106,7 @@ impl Allele {
     }
 
     // Mutate allele based on mutate rate and factor
-    pub fn mutate_val(&mut self, rng: &mut ThreadRng) {
+    pub fn mutate_val(&mut self, rng: &mut StdRng) {
         // Check if allele mutates based on mutate rate
         if rng.gen::<f32>() <= self.mutate_rate {
             let mf = self.mutate_factor;
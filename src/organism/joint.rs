@@ -7,6 +7,8 @@ use bevy_rapier2d::prelude::{
     Friction, GravityScale, LockedAxes, RigidBody,
 };
 
+use crate::collider_layer::{OrganismCollision, OrganismCollisionBundle};
+
 // Bundle for spawning an organisms joint
 #[derive(Bundle)]
 pub struct JointBundle {
@@ -18,8 +20,7 @@ pub struct JointBundle {
     damping: Damping,
     friction: Friction,
     collider: Collider,
-    // collision_layer: CollisionLayer,
-    // active_hooks: ActiveHooks,
+    collision: OrganismCollisionBundle,
     material_mesh_bundle: MaterialMesh2dBundle<ColorMaterial>,
     gravity: GravityScale,
     ccd: Ccd,
@@ -30,6 +31,7 @@ impl JointBundle {
         translation: Vec2,
         mesh: &Mesh2dHandle,
         material: &Handle<ColorMaterial>,
+        collision: &OrganismCollision,
     ) -> Self {
         let starting_damping = 10000000.0;
         let radius = 5.0;
@@ -47,6 +49,7 @@ impl JointBundle {
             },
             friction: Friction::coefficient(0.7),
             collider: Collider::ball(1.0),
+            collision: collision.get_bundle(),
             material_mesh_bundle: MaterialMesh2dBundle {
                 mesh: mesh.clone(),
                 material: material.clone(),
@@ -0,0 +1,176 @@
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::{
+    fs::{self, File},
+    io::BufReader,
+};
+
+use bevy::{
+    asset::Error,
+    prelude::{Resource, Vec2},
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    bone::JointType, brain::Brain, genome::Genome, motor::MotorParams, muscle::MuscleParams,
+    organism::OrganismBuilder,
+};
+
+// Folder blueprints are loaded from
+pub const LIBRARY_FOLDER: &str = "organisms";
+
+fn default_hidden_layers() -> Vec<usize> {
+    return vec![6, 6, 6];
+}
+
+// Named organism design, a brain is only saved with organisms that have been trained
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Blueprint {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    // Hidden layers of the brain made for the organism when there isn't one
+    #[serde(default = "default_hidden_layers")]
+    pub hidden_layers: Vec<usize>,
+    pub joints: Vec<Vec2>,
+    // Bones connect joints, muscles and motors connect bones
+    #[serde(default)]
+    pub bones: Vec<[usize; 2]>,
+    // How each end of each bone connects to its joint, revolute when not given
+    #[serde(default)]
+    pub connections: Vec<[JointType; 2]>,
+    #[serde(default)]
+    pub muscles: Vec<[usize; 2]>,
+    #[serde(default)]
+    pub motors: Vec<[usize; 2]>,
+    // Defaults are used when these don't match the muscles and motors
+    #[serde(default)]
+    pub muscle_params: Vec<MuscleParams>,
+    #[serde(default)]
+    pub motor_params: Vec<MotorParams>,
+    #[serde(default)]
+    pub genome: Option<Genome>,
+    #[serde(default)]
+    pub brain: Option<Brain>,
+    // Score the organism had when it was exported
+    #[serde(default)]
+    pub fitness: Option<f32>,
+}
+impl Blueprint {
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    pub fn load(path: &str) -> Result<Self, Error> {
+        return Err(Error::msg(format!("Can't load {} on the web", path)));
+    }
+
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn load(path: &str) -> Result<Self, Error> {
+        let file = File::open(path)?;
+        let reader = BufReader::new(file);
+        let blueprint: Self = serde_json::from_reader(reader)?;
+        blueprint.validate()?;
+        return Ok(blueprint);
+    }
+
+    // Every part has to connect parts that exist
+    fn validate(&self) -> Result<(), Error> {
+        let check = |kind: &str, parts: &Vec<[usize; 2]>, num_ends: usize| {
+            for (i, [a, b]) in parts.iter().enumerate() {
+                if *a >= num_ends || *b >= num_ends {
+                    return Err(Error::msg(format!(
+                        "{} {} of {:?} connects to a part that doesn't exist",
+                        kind, i, self.name
+                    )));
+                }
+            }
+            return Ok(());
+        };
+        check("Bone", &self.bones, self.joints.len())?;
+        check("Muscle", &self.muscles, self.bones.len())?;
+        check("Motor", &self.motors, self.bones.len())?;
+        return Ok(());
+    }
+
+    // A new brain is made when the saved brain can't drive the organism
+    pub fn get_builder(&self) -> OrganismBuilder {
+        let mut ob = OrganismBuilder::from_blueprint(self);
+        if let Some(brain) = &self.brain {
+            if let Err(err) = ob.set_brain(brain.clone()) {
+                println!("Not using the brain saved with {:?}, {}", self.name, err);
+            }
+        }
+        return ob;
+    }
+
+    // Fails when the saved brain can't drive the organism instead of making a new one
+    pub fn try_get_builder(&self) -> Result<OrganismBuilder, Error> {
+        let mut ob = OrganismBuilder::from_blueprint(self);
+        if let Some(brain) = &self.brain {
+            ob.set_brain(brain.clone()).map_err(|err| {
+                Error::msg(format!("Brain of {:?} can't be used, {}", self.name, err))
+            })?;
+        }
+        return Ok(ob);
+    }
+
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    pub fn save(&self, path: &str) -> Result<(), Error> {
+        return Err(Error::msg(format!("Can't save {} on the web", path)));
+    }
+
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn save(&self, path: &str) -> Result<(), Error> {
+        if let Some(folder) = std::path::Path::new(path).parent() {
+            fs::create_dir_all(folder)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        return Ok(());
+    }
+}
+
+// Blueprints that runs can be started from or opened in the constructor
+#[derive(Resource, Default)]
+pub struct OrganismLibrary {
+    pub blueprints: Vec<Blueprint>,
+    // Blueprint picked in the start menu
+    pub selected: Option<usize>,
+    // Blueprint to open when the constructor is spawned
+    pub editing: Option<usize>,
+}
+impl OrganismLibrary {
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    pub fn load(_folder: &str) -> Self {
+        return Self::default();
+    }
+
+    // Every blueprint in a folder in file name order, files that can't be read are skipped
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn load(folder: &str) -> Self {
+        let entries = match fs::read_dir(folder) {
+            Ok(e) => e,
+            Err(err) => {
+                println!("Error reading organism library {:?}, {:?}", folder, err);
+                return Self::default();
+            }
+        };
+        let mut paths = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().map_or(false, |e| e == "json"))
+            .collect::<Vec<_>>();
+        paths.sort();
+
+        let mut blueprints = vec![];
+        for path in paths {
+            match Blueprint::load(&path.to_string_lossy()) {
+                Ok(b) => blueprints.push(b),
+                Err(err) => println!("Error loading blueprint {:?}, {:?}", path, err),
+            }
+        }
+        return Self {
+            blueprints,
+            ..Default::default()
+        };
+    }
+
+    pub fn get_selected(&self) -> Option<&Blueprint> {
+        return self.selected.and_then(|i| self.blueprints.get(i));
+    }
+}
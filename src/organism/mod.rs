@@ -1,24 +1,43 @@
-use bevy::prelude::{resource_exists, IntoSystemConfigs, Plugin, Update};
+use bevy::prelude::{resource_exists, IntoSystemConfigs, Plugin, Startup, Update};
 
-use self::organism_list::{unfreeze_queued, update_brains, update_muscles, OrganismList};
+use self::{
+    fluid::{apply_fluid_forces, setup_medium_gravity},
+    organism_list::{
+        unfreeze_queued, update_brains, update_motor_energy, update_muscles, OrganismList,
+    },
+    perturbation::{apply_perturbations, Perturbations},
+};
 
 pub mod bone;
 pub mod brain;
+pub mod fluid;
 pub mod genome;
 pub mod helper_fn;
 pub mod joint;
+pub mod library;
+pub mod motor;
 pub mod muscle;
 pub mod organism;
 pub mod organism_list;
+pub mod perturbation;
 
 // Plugin to handle organisms
 pub struct OrganismPlugin;
 impl Plugin for OrganismPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
-        app.add_systems(
-            Update,
-            (update_brains, update_muscles, unfreeze_queued)
-                .run_if(resource_exists::<OrganismList>()),
-        );
+        app.insert_resource(Perturbations::new())
+            .add_systems(Startup, setup_medium_gravity)
+            .add_systems(
+                Update,
+                (
+                    update_brains,
+                    update_muscles,
+                    update_motor_energy,
+                    apply_fluid_forces,
+                    apply_perturbations.before(update_brains),
+                    unfreeze_queued,
+                )
+                    .run_if(resource_exists::<OrganismList>()),
+            );
     }
 }
@@ -0,0 +1,127 @@
+use bevy::prelude::{BuildChildren, Commands, Component, Entity, Vec2};
+use bevy_rapier2d::{
+    prelude::{ImpulseJoint, RevoluteJointBuilder},
+    rapier::prelude::JointAxis,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MotorMode {
+    // Brain output sets the target angle between the bones
+    Position,
+    // Brain output sets the target angular velocity between the bones
+    Velocity,
+}
+
+// Properties of a motorised joint, stored per motor in the builder so they can evolve
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MotorParams {
+    pub mode: MotorMode,
+    pub stiffness: f32,
+    pub damping: f32,
+    pub max_torque: f32,
+    // Brain output of 1.0 maps to this angle (radians) or angular velocity (radians/s)
+    pub range: f32,
+    // Optional min/max angle relative to the spawn pose
+    pub limits: Option<[f32; 2]>,
+}
+impl Default for MotorParams {
+    fn default() -> Self {
+        Self {
+            mode: MotorMode::Position,
+            stiffness: 5000.0,
+            damping: 200.0,
+            max_torque: 100000.0,
+            range: 1.0,
+            limits: None,
+        }
+    }
+}
+impl MotorParams {
+    pub fn mutate(&mut self, rng: &mut impl Rng, mr: f32, mf: f32) {
+        for val in [
+            &mut self.stiffness,
+            &mut self.damping,
+            &mut self.max_torque,
+            &mut self.range,
+        ] {
+            if rng.gen::<f32>() <= mr {
+                *val = (*val * (1.0 + rng.gen_range(-mf..mf))).max(0.001);
+            }
+        }
+        // Limits stay within a half turn either way with min below max
+        if let Some([min, max]) = &mut self.limits {
+            if rng.gen::<f32>() <= mr {
+                *min = (*min + rng.gen_range(-mf..mf)).clamp(-PI, PI);
+                *max = (*max + rng.gen_range(-mf..mf)).clamp(-PI, PI);
+                if *min > *max {
+                    std::mem::swap(min, max);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Motor {
+    pub bones: [Entity; 2],
+    pub params: MotorParams,
+    pub target: f32,
+}
+impl Motor {
+    // Spawn a motorised revolute joint between two bones pivoting around pivot_offsets
+    // The joint entity is parented to the second bone so it's despawned with it
+    pub fn spawn(
+        commands: &mut Commands,
+        bones: [Entity; 2],
+        pivot_offsets: [Vec2; 2],
+        params: &MotorParams,
+    ) -> Entity {
+        let mut builder = RevoluteJointBuilder::new()
+            .local_anchor1(pivot_offsets[0])
+            .local_anchor2(pivot_offsets[1])
+            .motor_max_force(params.max_torque);
+        builder = match params.mode {
+            MotorMode::Position => builder.motor_position(0.0, params.stiffness, params.damping),
+            MotorMode::Velocity => builder.motor_velocity(0.0, params.damping),
+        };
+        if let Some(limits) = params.limits {
+            builder = builder.limits(limits);
+        }
+
+        let motor_ent = commands
+            .spawn((
+                ImpulseJoint::new(bones[0], builder.build()),
+                Motor {
+                    bones,
+                    params: params.clone(),
+                    target: 0.0,
+                },
+            ))
+            .id();
+        commands.entity(bones[1]).add_child(motor_ent);
+
+        return motor_ent;
+    }
+
+    // Convert a brain output into a motor target and apply it to the joint
+    pub fn set_target(&mut self, joint: &mut ImpulseJoint, brain_out: f32) {
+        let p = &self.params;
+        self.target = brain_out * p.range;
+        match p.mode {
+            MotorMode::Position => {
+                joint
+                    .data
+                    .set_motor_position(JointAxis::AngX, self.target, p.stiffness, p.damping);
+            }
+            MotorMode::Velocity => {
+                joint
+                    .data
+                    .set_motor_velocity(JointAxis::AngX, self.target, p.damping);
+            }
+        }
+    }
+}
@@ -3,6 +3,8 @@ use bevy::{
     prelude::{default, Bundle, Commands, Component, Entity, Handle, Quat, Transform, Vec2},
     sprite::{ColorMaterial, MaterialMesh2dBundle, Mesh2dHandle},
 };
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 #[derive(Bundle)]
 pub struct MuscleBundle {
@@ -16,9 +18,18 @@ impl MuscleBundle {
         material: &Handle<ColorMaterial>,
         bones: [Entity; 2],
         bone_pos: [Vec2; 2],
+        bone_half_len: [Vec2; 2],
+        params: &MuscleParams,
     ) -> Entity {
-        let ab = bone_pos[1] - bone_pos[0];
-        // let dir = ab * 0.5;
+        // Offsets from each bone's centre to the muscle's attachment point
+        let anchors = [
+            bone_half_len[0] * params.attachments[0],
+            bone_half_len[1] * params.attachments[1],
+        ];
+        let a_pos = bone_pos[0] + anchors[0];
+        let b_pos = bone_pos[1] + anchors[1];
+
+        let ab = b_pos - a_pos;
         let len = ab.length();
         let x = if ab.x >= 0.0 { -1.0 } else { 1.0 };
         let z_rot = x * f32::acos(ab.y / len);
@@ -27,13 +38,12 @@ impl MuscleBundle {
             .spawn(MuscleBundle::new(
                 &mesh,
                 &material,
-                ab * 0.5,
+                a_pos + ab * 0.5,
                 len,
                 z_rot,
-                bones,
+                Muscle::new(bones, anchors, len, params.clone()),
             ))
             .id();
-        // commands.get_entity(bones[0]).unwrap().add_child(muscle_ent);
 
         return muscle_ent;
     }
@@ -44,7 +54,7 @@ impl MuscleBundle {
         translation: Vec2,
         len: f32,
         z_rot: f32,
-        bones: [Entity; 2],
+        muscle: Muscle,
     ) -> Self {
         let muscle_width = 2.0;
 
@@ -54,29 +64,123 @@ impl MuscleBundle {
                 material: material.clone(),
                 transform: Transform {
                     translation: translation.extend(-0.3),
-                    // translation: vec3(0.0, -len * 0.5, -0.2),
                     rotation: Quat::from_rotation_z(z_rot),
                     scale: vec3(muscle_width, len, 0.0),
                 },
                 ..default()
             },
-            muscle: Muscle {
-                bones,
-                base_len: len,
-                len_modifier: 0.0,
-            },
+            muscle,
         };
     }
 }
 
+// Physical properties of a muscle, stored per muscle in the builder so they can evolve
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MuscleParams {
+    // Force per unit of length away from the target length
+    pub stiffness: f32,
+    // Force per unit of lengthening velocity
+    pub damping: f32,
+    pub max_force: f32,
+    // Contraction velocity at which the muscle stops producing force
+    pub max_velocity: f32,
+    // Scale force with a Hill-type force-length-velocity curve
+    pub hill: bool,
+    // Where along each bone the muscle attaches, -1.0 and 1.0 being the bone ends
+    pub attachments: [f32; 2],
+}
+impl Default for MuscleParams {
+    fn default() -> Self {
+        Self {
+            stiffness: 60.0,
+            damping: 4.0,
+            max_force: 2400.0,
+            max_velocity: 400.0,
+            hill: false,
+            attachments: [0.0, 0.0],
+        }
+    }
+}
+impl MuscleParams {
+    pub fn mutate(&mut self, rng: &mut impl Rng, mr: f32, mf: f32) {
+        mutate_scale(rng, &mut self.stiffness, mr, mf);
+        mutate_scale(rng, &mut self.damping, mr, mf);
+        mutate_scale(rng, &mut self.max_force, mr, mf);
+        mutate_scale(rng, &mut self.max_velocity, mr, mf);
+
+        for a in self.attachments.iter_mut() {
+            if rng.gen::<f32>() <= mr {
+                *a = (*a + rng.gen_range(-mf..mf)).clamp(-1.0, 1.0);
+            }
+        }
+    }
+}
+
+// Scale a strictly positive parameter by a random factor
+fn mutate_scale(rng: &mut impl Rng, val: &mut f32, mr: f32, mf: f32) {
+    if rng.gen::<f32>() <= mr {
+        *val = (*val * (1.0 + rng.gen_range(-mf..mf))).max(0.001);
+    }
+}
+
 #[derive(Component)]
 pub struct Muscle {
     pub bones: [Entity; 2],
+    // Attachment offsets in each bone's local space
+    pub anchors: [Vec2; 2],
     pub base_len: f32,
     pub len_modifier: f32,
+    pub params: MuscleParams,
 }
 impl Muscle {
+    pub fn new(
+        bones: [Entity; 2],
+        anchors: [Vec2; 2],
+        base_len: f32,
+        params: MuscleParams,
+    ) -> Self {
+        return Self {
+            bones,
+            anchors,
+            base_len,
+            len_modifier: 0.0,
+            params,
+        };
+    }
+
     pub fn get_target_len(&self) -> f32 {
         return self.base_len + (self.base_len * self.len_modifier);
     }
+
+    // Tension along the muscle, positive values pull the attachment points together
+    // len_vel is the rate the muscle is lengthening at
+    pub fn get_tension(&self, len: f32, len_vel: f32) -> f32 {
+        let p = &self.params;
+        let target_len = self.get_target_len();
+
+        let mut tension = p.stiffness * (len - target_len) + p.damping * len_vel;
+
+        if p.hill {
+            // Active force peaks at the resting length and falls off either side
+            let x = (len / self.base_len - 1.0) / 0.45;
+            let force_len = f32::exp(-x * x);
+
+            // Shortening reduces force, lengthening increases it up to 1.5x
+            let v = (-len_vel / p.max_velocity).clamp(-1.0, 1.0);
+            let force_vel = match v >= 0.0 {
+                true => (1.0 - v) / (1.0 + 4.0 * v),
+                false => 1.5 - 0.5 * (1.0 + v) / (1.0 - 7.56 * v),
+            };
+
+            tension *= force_len * force_vel;
+        } else if tension > 0.0 && -len_vel >= p.max_velocity {
+            // Already contracting as fast as the muscle can
+            tension = 0.0;
+        } else if tension < 0.0 && len_vel >= p.max_velocity {
+            tension = 0.0;
+        }
+
+        return tension.clamp(-p.max_force, p.max_force);
+    }
 }
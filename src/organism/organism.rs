@@ -1,14 +1,23 @@
+use std::collections::VecDeque;
+
 use bevy::{
+    asset::Error,
     math::vec2,
     prelude::{Commands, DespawnRecursiveExt, Entity, Resource, Vec2},
 };
-use rand::{rngs::ThreadRng, Rng};
+use rand::{rngs::StdRng, Rng};
 use serde::{Deserialize, Serialize};
 
-use crate::handles::Handles;
+use crate::{collider_layer::OrganismCollision, generation::lineage::Lineage, handles::Handles};
 
 use super::{
-    bone::BoneBundle, brain::Brain, genome::Genome, joint::JointBundle, muscle::MuscleBundle,
+    bone::{BoneBundle, JointType},
+    brain::Brain,
+    genome::Genome,
+    joint::JointBundle,
+    library::Blueprint,
+    motor::{Motor, MotorParams},
+    muscle::{MuscleBundle, MuscleParams},
 };
 
 // Acts as a blueprint for organisms so mutations can occur before spawning
@@ -18,7 +27,18 @@ pub struct OrganismBuilder {
     genome: Genome,
     joint_pos: Vec<Vec2>,
     bones: Vec<[usize; 2]>,
+    // How each end of each bone connects to its joint
+    #[serde(default)]
+    connections: Vec<[JointType; 2]>,
     muscles: Vec<[usize; 2]>,
+    #[serde(default)]
+    muscle_params: Vec<MuscleParams>,
+    #[serde(default)]
+    motors: Vec<[usize; 2]>,
+    #[serde(default)]
+    motor_params: Vec<MotorParams>,
+    #[serde(default)]
+    pub lineage: Lineage,
 }
 impl OrganismBuilder {
     // Create new builder
@@ -27,26 +47,140 @@ impl OrganismBuilder {
         brain_hidden_structure: Vec<usize>,
         joint_pos: Vec<Vec2>,
         bones: Vec<[usize; 2]>,
+        connections: Vec<[JointType; 2]>,
         muscles: Vec<[usize; 2]>,
+        motors: Vec<[usize; 2]>,
     ) -> Self {
-        // Get num muscles
+        // Every muscle and motor is driven by one brain output
         let num_muscles = muscles.len();
-        // let num_outputs = num_muscles * 4;
+        let num_motors = motors.len();
+        let num_actuators = num_muscles + num_motors;
 
         // Calculate brain structure
-        let mut brain_structure = vec![external_stimuli_count + num_muscles * 5];
+        let mut brain_structure = vec![external_stimuli_count + num_actuators * 5];
         brain_structure.extend(brain_hidden_structure);
-        brain_structure.push(num_muscles);
+        brain_structure.push(num_actuators);
 
         return Self {
             brain: Brain::new(brain_structure),
             genome: Genome::default(),
             joint_pos,
             bones,
+            connections,
             muscles,
+            muscle_params: vec![MuscleParams::default(); num_muscles],
+            motors,
+            motor_params: vec![MotorParams::default(); num_motors],
+            lineage: Lineage::default(),
         };
     }
 
+    // Resize the brain's input layer for the sensors a task adds
+    pub fn set_num_task_stimuli(&mut self, num_task_stimuli: usize) {
+        // Memory, time and the bone rotations of each actuator come before task sensors
+        let num_actuators = self.muscles.len() + self.motors.len();
+        self.brain
+            .set_num_inputs(num_actuators * 5 + 1 + num_task_stimuli);
+    }
+
+    // Limits of each motor set in the constructor, in the same order as the motors
+    pub fn set_motor_limits(&mut self, limits: Vec<Option<[f32; 2]>>) {
+        for (p, l) in self.motor_params.iter_mut().zip(limits) {
+            p.limits = l;
+        }
+    }
+
+    pub fn get_brain(&self) -> &Brain {
+        return &self.brain;
+    }
+
+    // Use a trained brain, it needs an output for every muscle and motor and an input for every
+    // body sensor, inputs after those were task sensors and are kept for the task to resize
+    pub fn set_brain(&mut self, brain: Brain) -> Result<(), Error> {
+        brain.validate()?;
+        let num_actuators = self.muscles.len() + self.motors.len();
+        if brain.get_num_outputs() != num_actuators {
+            return Err(Error::msg(format!(
+                "Brain has {} outputs but there are {} muscles and motors",
+                brain.get_num_outputs(),
+                num_actuators
+            )));
+        }
+        // Memory, time and the bone rotations of each actuator
+        let num_body_inputs = num_actuators * 5 + 1;
+        if brain.get_num_inputs() < num_body_inputs {
+            return Err(Error::msg(format!(
+                "Brain has {} inputs but the organism has {} sensors",
+                brain.get_num_inputs(),
+                num_body_inputs
+            )));
+        }
+        self.brain = brain;
+        return Ok(());
+    }
+
+    // Builder with a new brain, the blueprint's brain has to be set separately as it might not
+    // fit
+    pub fn from_blueprint(bp: &Blueprint) -> Self {
+        let mut ob = Self::new(
+            1,
+            bp.hidden_layers.clone(),
+            bp.joints.clone(),
+            bp.bones.clone(),
+            bp.connections.clone(),
+            bp.muscles.clone(),
+            bp.motors.clone(),
+        );
+        if let Some(genome) = &bp.genome {
+            ob.genome = genome.clone();
+        }
+        if bp.muscle_params.len() == ob.muscles.len() {
+            ob.muscle_params = bp.muscle_params.clone();
+        }
+        if bp.motor_params.len() == ob.motors.len() {
+            ob.motor_params = bp.motor_params.clone();
+        }
+        return ob;
+    }
+
+    // Everything needed to bring the organism back in another run
+    pub fn to_blueprint(
+        &self,
+        name: String,
+        description: String,
+        fitness: Option<f32>,
+    ) -> Blueprint {
+        return Blueprint {
+            name,
+            description,
+            hidden_layers: self.brain.get_hidden_layers(),
+            joints: self.joint_pos.clone(),
+            bones: self.bones.clone(),
+            connections: self.connections.clone(),
+            muscles: self.muscles.clone(),
+            motors: self.motors.clone(),
+            muscle_params: self.muscle_params.clone(),
+            motor_params: self.motor_params.clone(),
+            genome: Some(self.genome.clone()),
+            brain: Some(self.brain.clone()),
+            fitness,
+        };
+    }
+
+    pub fn get_bones(&self) -> &Vec<[usize; 2]> {
+        return &self.bones;
+    }
+
+    // Number of joints, bones, muscles and motors
+    pub fn get_part_counts(&self) -> [usize; 4] {
+        return [
+            self.joint_pos.len(),
+            self.bones.len(),
+            self.muscles.len(),
+            self.motors.len(),
+        ];
+    }
+
     // Spawn the organism with an translation
     pub fn spawn(
         &self,
@@ -55,12 +189,14 @@ impl OrganismBuilder {
         // materials: &mut Assets<ColorMaterial>,
         handles: &Handles,
         translation: Vec2,
+        collision: OrganismCollision,
     ) -> Organism {
         // Pre-allocate vectors
         let mut joint_ents = Vec::with_capacity(self.joint_pos.len());
         let mut bone_ents = Vec::with_capacity(self.bones.len());
         let mut bone_pos = Vec::with_capacity(self.bones.len());
         let mut muscles_ents = Vec::with_capacity(self.muscles.len());
+        let mut motor_ents = Vec::with_capacity(self.motors.len());
 
         // Create a joint for each position supplied
         for jp in self.joint_pos.iter() {
@@ -69,13 +205,18 @@ impl OrganismBuilder {
                     translation + *jp,
                     &handles.joint_mesh,
                     &handles.joint_material,
+                    &collision,
                 ))
                 .id();
             joint_ents.push(ent);
         }
 
         // Create a bone for each bone given
-        for [j_a, j_b] in self.bones.iter() {
+        for (i, [j_a, j_b]) in self.bones.iter().enumerate() {
+            let connections = match self.connections.get(i) {
+                Some(c) => *c,
+                None => [JointType::Revolute; 2],
+            };
             let bone = BoneBundle::spawn(
                 commands,
                 &handles.bone_mesh,
@@ -85,39 +226,128 @@ impl OrganismBuilder {
                     translation + self.joint_pos[*j_a],
                     translation + self.joint_pos[*j_b],
                 ],
+                connections,
+                &collision,
             );
             bone_ents.push(bone.0);
             bone_pos.push(bone.1);
         }
 
         // Create a muscle for each muscle given
-        for [j_a, j_b] in self.muscles.iter() {
+        for (i, [b_a, b_b]) in self.muscles.iter().enumerate() {
+            let params = match self.muscle_params.get(i) {
+                Some(p) => p.clone(),
+                None => MuscleParams::default(),
+            };
             let m = MuscleBundle::spawn(
                 commands,
                 &handles.muscle_mesh,
                 &handles.muscle_neutral_material,
-                [bone_ents[*j_a], bone_ents[*j_b]],
-                [bone_pos[*j_a], bone_pos[*j_b]],
+                [bone_ents[*b_a], bone_ents[*b_b]],
+                [bone_pos[*b_a], bone_pos[*b_b]],
+                [self.get_bone_half_len(*b_a), self.get_bone_half_len(*b_b)],
+                &params,
             );
             muscles_ents.push(m);
         }
 
+        // Create a motor for each motor given
+        for (i, [b_a, b_b]) in self.motors.iter().enumerate() {
+            let params = match self.motor_params.get(i) {
+                Some(p) => p.clone(),
+                None => MotorParams::default(),
+            };
+            let pivot = self.get_motor_pivot(*b_a, *b_b);
+            let m = Motor::spawn(
+                commands,
+                [bone_ents[*b_a], bone_ents[*b_b]],
+                [
+                    pivot - (bone_pos[*b_a] - translation),
+                    pivot - (bone_pos[*b_b] - translation),
+                ],
+                &params,
+            );
+            motor_ents.push(m);
+        }
+
         return Organism {
             brain: self.brain.clone(),
             genome: self.genome.clone(),
             joints: joint_ents,
             bones: bone_ents,
             muscles: muscles_ents,
+            motors: motor_ents,
             energy_used: 0.0,
             freeze_progress: 0.0,
+            finish_time: None,
+            group: 0,
+            start_pos: translation + self.get_centre(),
+            output_delay: VecDeque::new(),
         };
     }
 
-    // Mutate the builder
-    pub fn mutate(&mut self, rng: &mut ThreadRng) {
+    // Mean position of the joints relative to the spawn translation
+    fn get_centre(&self) -> Vec2 {
+        if self.joint_pos.is_empty() {
+            return Vec2::ZERO;
+        }
+        return self.joint_pos.iter().sum::<Vec2>() / self.joint_pos.len() as f32;
+    }
+
+    // Vector from the centre of a bone to its second joint
+    fn get_bone_half_len(&self, bone: usize) -> Vec2 {
+        let [j_a, j_b] = self.bones[bone];
+        return (self.joint_pos[j_b] - self.joint_pos[j_a]) * 0.5;
+    }
+
+    // Position the motor between two bones pivots around
+    // Uses the joint the bones share, otherwise the closest pair of joints
+    fn get_motor_pivot(&self, b_a: usize, b_b: usize) -> Vec2 {
+        let mut closest = (f32::MAX, Vec2::ZERO);
+        for j_a in self.bones[b_a] {
+            for j_b in self.bones[b_b] {
+                let pos_a = self.joint_pos[j_a];
+                let pos_b = self.joint_pos[j_b];
+                let dist = pos_a.distance(pos_b);
+                if dist < closest.0 {
+                    closest = (dist, (pos_a + pos_b) * 0.5);
+                }
+            }
+        }
+        return closest.1;
+    }
+
+    // Mutate the builder, parameters change every time so only joint moves and structural
+    // changes are recorded in the lineage
+    pub fn mutate(&mut self, rng: &mut StdRng) {
+        self.lineage.mutations.clear();
+
         // Mutate genome
         self.genome.mutate(rng);
 
+        // Mutate muscle properties, older builders may not have any stored
+        self.muscle_params
+            .resize(self.muscles.len(), MuscleParams::default());
+        let mr = self.genome.muscle_param_mr.val;
+        let mf = self.genome.muscle_param_mf.val;
+        for p in self.muscle_params.iter_mut() {
+            p.mutate(rng, mr, mf);
+        }
+        self.motor_params
+            .resize(self.motors.len(), MotorParams::default());
+        for p in self.motor_params.iter_mut() {
+            p.mutate(rng, mr, mf);
+        }
+
+        // Mutate joint limits
+        self.connections
+            .resize(self.bones.len(), [JointType::Revolute; 2]);
+        let mr = self.genome.joint_limit_mr.val;
+        let mf = self.genome.joint_limit_mf.val;
+        for c in self.connections.iter_mut().flatten() {
+            c.mutate(rng, mr, mf);
+        }
+
         // Mutate brain
         self.brain
             .learn(rng, self.genome.lr.val, self.genome.lf.val);
@@ -127,35 +357,50 @@ impl OrganismBuilder {
             if rng.gen::<f32>() <= self.genome.joint_mr.val {
                 let mf = self.genome.joint_mf.val;
                 self.move_joint(rng, i, mf);
+                self.lineage.mutations.push(format!("move joint {}", i));
             }
         }
 
         return;
         // Add/remove bone
         if rng.gen::<f32>() <= self.genome.bone_mr.val {
-            match rng.gen::<f32>() <= 0.5 {
-                true => self.add_bone(rng, self.genome.muscle_mr.val),
-                false => self.remove_bone(rng),
-            }
+            let mutation = match rng.gen::<f32>() <= 0.5 {
+                true => {
+                    self.add_bone(rng, self.genome.muscle_mr.val);
+                    "add bone"
+                }
+                false => {
+                    self.remove_bone(rng);
+                    "remove bone"
+                }
+            };
+            self.lineage.mutations.push(mutation.to_string());
         }
 
         // Add/remove muscle
         if rng.gen::<f32>() <= self.genome.muscle_mr.val {
-            match rng.gen::<f32>() <= 0.5 {
-                true => self.add_muscle(rng),
-                false => self.remove_muscle(rng),
-            }
+            let mutation = match rng.gen::<f32>() <= 0.5 {
+                true => {
+                    self.add_muscle(rng);
+                    "add muscle"
+                }
+                false => {
+                    self.remove_muscle(rng);
+                    "remove muscle"
+                }
+            };
+            self.lineage.mutations.push(mutation.to_string());
         }
     }
 
-    pub fn move_joint(&mut self, rng: &mut ThreadRng, i: usize, mf: f32) {
+    pub fn move_joint(&mut self, rng: &mut StdRng, i: usize, mf: f32) {
         let dx = rng.gen_range(-mf..mf);
         let dy = rng.gen_range(-mf..mf);
         let unclamped = self.joint_pos[i] + vec2(dx, dy);
         self.joint_pos[i] = unclamped.clamp(vec2(-100.0, 0.0), vec2(100.0, 200.0));
     }
 
-    pub fn add_bone(&mut self, rng: &mut ThreadRng, mf: f32) {
+    pub fn add_bone(&mut self, rng: &mut StdRng, mf: f32) {
         let num_joints = self.joint_pos.len();
         let from = rng.gen_range(0..num_joints);
 
@@ -164,9 +409,10 @@ impl OrganismBuilder {
 
         let bone = [from, num_joints - 1];
         self.bones.push(bone);
+        self.connections.push([JointType::Revolute; 2]);
     }
 
-    pub fn remove_bone(&mut self, rng: &mut ThreadRng) {
+    pub fn remove_bone(&mut self, rng: &mut StdRng) {
         let num_bones = self.bones.len();
         if num_bones == 0 {
             return;
@@ -183,9 +429,12 @@ impl OrganismBuilder {
         }
         println!("removed bone at index {:?}", index);
         self.bones.remove(index);
+        if index < self.connections.len() {
+            self.connections.remove(index);
+        }
     }
 
-    pub fn add_muscle(&mut self, rng: &mut ThreadRng) {
+    pub fn add_muscle(&mut self, rng: &mut StdRng) {
         let num_joints = self.joint_pos.len();
         if num_joints < 2 {
             return;
@@ -200,9 +449,10 @@ impl OrganismBuilder {
 
         self.brain.add_io();
         self.muscles.push([a, b]);
+        self.muscle_params.push(MuscleParams::default());
     }
 
-    pub fn remove_muscle(&mut self, rng: &mut ThreadRng) {
+    pub fn remove_muscle(&mut self, rng: &mut StdRng) {
         let num_muscles = self.muscles.len();
         if num_muscles == 0 {
             return;
@@ -211,6 +461,9 @@ impl OrganismBuilder {
 
         self.brain.remove_io();
         self.muscles.remove(index);
+        if index < self.muscle_params.len() {
+            self.muscle_params.remove(index);
+        }
     }
 }
 
@@ -222,8 +475,18 @@ pub struct Organism {
     pub joints: Vec<Entity>,
     pub bones: Vec<Entity>,
     pub muscles: Vec<Entity>,
+    // Motors are children of bones and are despawned with them
+    pub motors: Vec<Entity>,
     pub energy_used: f32,
     pub freeze_progress: f32,
+    // Seconds into the generation the organism crossed the finish line
+    pub finish_time: Option<f32>,
+    // Index of the environment the organism runs in when trials run in parallel
+    pub group: usize,
+    // Mean position of the joints when spawned
+    pub start_pos: Vec2,
+    // Brain outputs waiting to reach the actuators
+    pub output_delay: VecDeque<Vec<f32>>,
 }
 
 impl Organism {
@@ -256,3 +519,43 @@ impl Organism {
         return brain_out;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_builder() -> OrganismBuilder {
+        return OrganismBuilder::new(
+            1,
+            vec![4],
+            vec![vec2(0.0, 0.0), vec2(40.0, 0.0), vec2(80.0, 20.0)],
+            vec![[0, 1], [1, 2]],
+            vec![[JointType::Revolute; 2]; 2],
+            vec![[0, 1]],
+            vec![],
+        );
+    }
+
+    #[test]
+    fn task_brain_round_trip() {
+        let mut ob = get_builder();
+        ob.set_num_task_stimuli(3);
+        let bp = ob.to_blueprint("test".to_string(), String::new(), None);
+
+        let mut loaded = bp.try_get_builder().unwrap();
+        assert_eq!(loaded.get_brain().get_num_inputs(), 5 + 1 + 3);
+        assert_eq!(loaded.get_brain().get_params(), ob.get_brain().get_params());
+
+        // Spawning for the same task leaves the task sensor weights alone
+        loaded.set_num_task_stimuli(3);
+        assert_eq!(loaded.get_brain().get_params(), ob.get_brain().get_params());
+    }
+
+    #[test]
+    fn brain_missing_sensors_is_rejected() {
+        let mut ob = get_builder();
+        let mut brain = ob.get_brain().clone();
+        brain.set_num_inputs(3);
+        assert!(ob.set_brain(brain).is_err());
+    }
+}
@@ -1,19 +1,25 @@
 use bevy::{
-    math::vec2,
-    prelude::{Commands, Handle, Quat, Query, Res, ResMut, Resource, Transform, With, Without},
+    prelude::{
+        Commands, Handle, Quat, Query, Res, ResMut, Resource, Transform, Vec2, With, Without,
+    },
     sprite::ColorMaterial,
     time::Time,
 };
-use bevy_rapier2d::prelude::{Damping, ExternalImpulse};
+use bevy_rapier2d::prelude::{Damping, ExternalImpulse, ImpulseJoint, RapierContext, Velocity};
 
-use crate::{config::structs::GenerationConfig, handles::Handles};
+use crate::{
+    collider_layer::OrganismCollision, config::structs::GenerationConfig,
+    generation::environment::Environment, handles::Handles, task::TaskState,
+};
 
 use super::{
     bone::Bone,
     helper_fn::{quat_to_vec2, vec2_z_rot},
     joint::Joint,
+    motor::Motor,
     muscle::Muscle,
     organism::{Organism, OrganismBuilder},
+    perturbation::Perturbations,
 };
 
 // Contains every organism
@@ -22,6 +28,22 @@ pub struct OrganismList {
     pub builders: Vec<OrganismBuilder>,
     pub organisms: Vec<Organism>,
     pub is_spawned: bool,
+    // Organisms before this index are on one team and the rest on the other, teams
+    // collide with each other
+    pub team_split: Option<usize>,
+    // Last evaluated builders, saved instead of the unevaluated builders when set
+    pub scored: Option<ScoredBuilders>,
+    // Best builder of the last evaluated generation and its score
+    pub best: Option<(OrganismBuilder, f32)>,
+    // Organism picked in the simulation, cleared when organisms are despawned
+    pub selected: Option<usize>,
+}
+
+// Builders of a generation with the score each got
+pub struct ScoredBuilders {
+    pub generation: u32,
+    pub builders: Vec<OrganismBuilder>,
+    pub scores: Vec<f32>,
 }
 impl OrganismList {
     pub fn new() -> Self {
@@ -29,6 +51,10 @@ impl OrganismList {
             builders: vec![],
             organisms: vec![],
             is_spawned: false,
+            team_split: None,
+            scored: None,
+            best: None,
+            selected: None,
         };
     }
 
@@ -47,26 +73,50 @@ impl OrganismList {
     // Despawn every organism
     pub fn despawn(&mut self, commands: &mut Commands) {
         self.is_spawned = false;
+        self.selected = None;
         for o in self.organisms.iter() {
             o.despawn(commands);
         }
     }
 
-    // Spawn every organism using the builders
-    pub fn spawn(&mut self, commands: &mut Commands, handles: &Handles, vertical_sep: f32) {
-        let mut cur_translation = vec2(0.0, vertical_sep * 0.15);
+    // Spawn every organism using the builders, each builder is spawned once for every
+    // trial in the environment
+    pub fn spawn(
+        &mut self,
+        commands: &mut Commands,
+        handles: &Handles,
+        gc: &GenerationConfig,
+        env: &Environment,
+        num_task_stimuli: usize,
+    ) {
+        let self_collision = gc.self_collision;
+        for b in self.builders.iter_mut() {
+            b.set_num_task_stimuli(num_task_stimuli);
+        }
+        let num_builders = self.builders.len();
+        let num_groups = env.trials.len();
 
         // Pre-allocate organisms vec
         // self.organisms.clear();
-        let num_organisms = self.builders.len();
+        let num_organisms = num_builders * num_groups;
         self.organisms = Vec::with_capacity(num_organisms);
 
-        // Spawn and push organism to vec
+        // Spawn and push organism to vec, each organism gets its own collision layer
         for i in 0..num_organisms {
-            self.organisms
-                .push(self.builders[i].spawn(commands, &handles, cur_translation));
-            // .push(self.builders[i].spawn(commands, vec2(0.0, 0.0), i as u32));
-            cur_translation.y += vertical_sep;
+            let group = i / num_builders;
+            let index = i % num_builders;
+            let mut collision = OrganismCollision::new(i as u32, self_collision);
+            if let Some(split) = self.team_split {
+                collision = collision.with_team((index >= split) as usize);
+            }
+            let mut organism = self.builders[index].spawn(
+                commands,
+                &handles,
+                env.get_spawn(group, index),
+                collision,
+            );
+            organism.group = group;
+            self.organisms.push(organism);
         }
         self.is_spawned = true;
     }
@@ -115,48 +165,61 @@ pub fn unfreeze_queued(
     }
 }
 
-// Update muscle lengths
+// Apply muscle tension to the bones they're attached to
 pub fn update_muscles(
     ol: Res<OrganismList>,
     handles: Res<Handles>,
-    mut bones: Query<(&mut ExternalImpulse, &Transform), With<Bone>>,
+    time: Res<Time>,
+    mut bones: Query<(&mut ExternalImpulse, &Transform, &Velocity), With<Bone>>,
     mut muscles: Query<(&Muscle, &mut Transform, &mut Handle<ColorMaterial>), Without<Bone>>,
 ) {
     // Short circuit if organisms haven't spawned;
     if !ol.is_spawned {
         return;
     }
+    let dt = time.delta_seconds();
+
+    // Several muscles can pull on the same bone so impulses are accumulated
+    for (mut ei, _, _) in bones.iter_mut() {
+        ei.impulse = Vec2::ZERO;
+        ei.torque_impulse = 0.0;
+    }
 
-    // let now = Instant::now();
     for (m, mut t, mut cm) in muscles.iter_mut() {
         match bones.get_many_mut(m.bones) {
-            Ok([(mut a_ei, a_t), (mut b_ei, b_t)]) => {
-                // readout(a_t, b_t);
-                // Apply impulse to joints
-                let a_pos = a_t.translation.truncate();
-                let b_pos = b_t.translation.truncate();
+            Ok([(mut a_ei, a_t, a_v), (mut b_ei, b_t, b_v)]) => {
+                // Attachment points relative to each bone's centre
+                let a_r = (a_t.rotation * m.anchors[0].extend(0.0)).truncate();
+                let b_r = (b_t.rotation * m.anchors[1].extend(0.0)).truncate();
+                let a_pos = a_t.translation.truncate() + a_r;
+                let b_pos = b_t.translation.truncate() + b_r;
                 let ab = b_pos - a_pos;
                 let len = ab.length();
-                let target_len = m.get_target_len();
-                let diff = target_len - len;
-
-                let contract_expand = if diff > 0.01 {
-                    *cm = handles.muscle_contract_material.clone();
-                    1.0
-                } else if diff < -0.01 {
-                    *cm = handles.muscle_expand_material.clone();
-                    -1.0
+                if len <= f32::EPSILON {
+                    continue;
+                }
+                let dir = ab / len;
+
+                // Rate the muscle is lengthening at
+                let a_point_vel = a_v.linvel + a_r.perp() * a_v.angvel;
+                let b_point_vel = b_v.linvel + b_r.perp() * b_v.angvel;
+                let len_vel = (b_point_vel - a_point_vel).dot(dir);
+
+                let tension = m.get_tension(len, len_vel);
+                *cm = if tension > 0.01 {
+                    handles.muscle_contract_material.clone()
+                } else if tension < -0.01 {
+                    handles.muscle_expand_material.clone()
                 } else {
-                    *cm = handles.muscle_neutral_material.clone();
-                    0.0
+                    handles.muscle_neutral_material.clone()
                 };
 
-                let modifier = 40.0;
-
-                if diff != 0.0 {
-                    a_ei.impulse = ab.normalize() * contract_expand * modifier;
-                    b_ei.impulse = -ab.normalize() * contract_expand * modifier;
-                }
+                // Apply impulse at the attachment points
+                let impulse = dir * tension * dt;
+                a_ei.impulse += impulse;
+                a_ei.torque_impulse += a_r.perp_dot(impulse);
+                b_ei.impulse -= impulse;
+                b_ei.torque_impulse -= b_r.perp_dot(impulse);
 
                 t.translation = (a_pos + (ab * 0.5)).extend(-0.3);
                 t.rotation = Quat::from_rotation_z(vec2_z_rot(&b_pos, &a_pos));
@@ -172,7 +235,10 @@ pub fn update_muscles(
 pub fn update_brains(
     mut ol: ResMut<OrganismList>,
     gc: Res<GenerationConfig>,
+    perturbations: Res<Perturbations>,
+    ts: Res<TaskState>,
     mut muscles: Query<&mut Muscle>,
+    mut motors: Query<(&mut Motor, &mut ImpulseJoint)>,
     bones: Query<&Transform, With<Bone>>,
 ) {
     // let now = Instant::now();
@@ -185,17 +251,28 @@ pub fn update_brains(
     // Gather global
     let elapsed_seconds = gc.timer.elapsed_secs();
 
-    for o in ol.organisms.iter_mut() {
+    for (i, o) in ol.organisms.iter_mut().enumerate() {
         let mut stimuli = Vec::with_capacity(o.brain.get_num_inputs());
         stimuli.push(elapsed_seconds);
 
-        let mut muscled_bone_rots = Vec::with_capacity(o.muscles.len() * 4);
+        // Gather the bones each actuator is attached to, muscles first then motors
+        let mut actuated_bones = Vec::with_capacity(o.muscles.len() + o.motors.len());
         for m_ent in o.muscles.iter() {
-            let m = match muscles.get(*m_ent) {
-                Ok(m) => m,
+            match muscles.get(*m_ent) {
+                Ok(m) => actuated_bones.push(m.bones),
                 Err(_) => return,
             };
-            let bone_trans = match bones.get_many(m.bones) {
+        }
+        for m_ent in o.motors.iter() {
+            match motors.get(*m_ent) {
+                Ok((m, _)) => actuated_bones.push(m.bones),
+                Err(_) => return,
+            };
+        }
+
+        let mut actuated_bone_rots = Vec::with_capacity(actuated_bones.len() * 4);
+        for b in actuated_bones {
+            let bone_trans = match bones.get_many(b) {
                 Ok(b_t) => b_t,
                 Err(_) => return,
             };
@@ -203,25 +280,90 @@ pub fn update_brains(
             let vec_a = quat_to_vec2(&bone_trans[0].rotation);
             let vec_b = quat_to_vec2(&bone_trans[1].rotation);
 
-            muscled_bone_rots.push(vec_a.x);
-            muscled_bone_rots.push(vec_a.y);
-            muscled_bone_rots.push(vec_b.x);
-            muscled_bone_rots.push(vec_b.y);
+            actuated_bone_rots.push(vec_a.x);
+            actuated_bone_rots.push(vec_a.y);
+            actuated_bone_rots.push(vec_b.x);
+            actuated_bone_rots.push(vec_b.y);
+        }
+
+        stimuli.extend(actuated_bone_rots);
+
+        // Sensor noise skips the time input
+        for (s, n) in stimuli
+            .iter_mut()
+            .skip(1)
+            .zip(perturbations.sensor_noise.iter())
+        {
+            *s += n;
         }
 
-        stimuli.extend(muscled_bone_rots);
+        // Task sensors go after every other input
+        if let Some(task_stimuli) = ts.stimuli.get(i) {
+            stimuli.extend(task_stimuli);
+        }
 
         // Process stimuli
         let brain_out = o.process_stimuli(&mut stimuli);
+        o.brain.set_memory(brain_out.clone());
+
+        // Outputs reach the actuators after the configured delay
+        o.output_delay.push_back(brain_out);
+        if o.output_delay.len() <= gc.perturbation.get_actuator_delay() {
+            continue;
+        }
+        let brain_out = o.output_delay.pop_front().unwrap();
 
+        let num_muscles = o.muscles.len();
         for i in 0..brain_out.len() {
-            let cur_len_modifier = &mut muscles.get_mut(o.muscles[i]).unwrap().len_modifier;
-            o.energy_used += (*cur_len_modifier - brain_out[i]).abs().sqrt();
-            *cur_len_modifier = brain_out[i];
+            if i < num_muscles {
+                let cur_len_modifier = &mut muscles.get_mut(o.muscles[i]).unwrap().len_modifier;
+                o.energy_used += (*cur_len_modifier - brain_out[i]).abs().sqrt();
+                *cur_len_modifier = brain_out[i];
+            } else {
+                // Motor energy is measured from torque in update_motor_energy
+                let (mut m, mut joint) = motors.get_mut(o.motors[i - num_muscles]).unwrap();
+                m.set_target(&mut joint, brain_out[i]);
+            }
         }
-        o.brain.set_memory(brain_out);
     }
 
     // println!("processing stimuli took {:?}", total_brain_process);
     // println!("update_brains took {:?}", now.elapsed());
 }
+
+// Accumulate the work done by each motor from the torque rapier applied last step
+pub fn update_motor_energy(
+    mut ol: ResMut<OrganismList>,
+    rapier_context: Res<RapierContext>,
+    motors: Query<&Motor>,
+    bones: Query<&Velocity, With<Bone>>,
+) {
+    if !ol.is_spawned {
+        return;
+    }
+
+    for o in ol.organisms.iter_mut() {
+        for m_ent in o.motors.iter() {
+            let m = match motors.get(*m_ent) {
+                Ok(m) => m,
+                Err(_) => continue,
+            };
+            let handle = match rapier_context.entity2impulse_joint().get(m_ent) {
+                Some(h) => *h,
+                None => continue,
+            };
+            let joint = match rapier_context.impulse_joints.get(handle) {
+                Some(j) => j,
+                None => continue,
+            };
+            let [a_v, b_v] = match bones.get_many(m.bones) {
+                Ok(v) => v,
+                Err(_) => continue,
+            };
+
+            // Angular impulse multiplied by relative angular velocity
+            let torque_impulse = joint.impulses.z;
+            o.energy_used += (torque_impulse * (b_v.angvel - a_v.angvel)).abs();
+        }
+    }
+}
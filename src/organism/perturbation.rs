@@ -0,0 +1,198 @@
+use bevy::{
+    math::vec2,
+    prelude::{Query, Res, ResMut, Resource, Transform, Vec2, With},
+};
+use bevy_rapier2d::prelude::ExternalImpulse;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::config::structs::GenerationConfig;
+
+use super::{joint::Joint, organism::Organism, organism_list::OrganismList};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct PerturbationConfig {
+    pub enable: bool,
+    pub seed: u64,
+    // Seconds before the first push, gives organisms time to settle
+    pub start_delay: f32,
+    // Mean seconds between pushes
+    pub interval: f32,
+    // Max random change to each interval
+    pub interval_jitter: f32,
+    // Max impulse of a push, spread over the organism's joints
+    pub max_impulse: f32,
+    // Max value of the uniform noise added to every sensor
+    pub sensor_noise: f32,
+    // Ticks between the brain deciding an output and actuators receiving it
+    pub actuator_delay: usize,
+    // Seconds after a push that recovery is measured over
+    pub recovery_time: f32,
+    // Weight of the distance travelled while recovering from pushes in each trial's score
+    pub robustness_weight: f32,
+}
+impl PerturbationConfig {
+    pub fn get_actuator_delay(&self) -> usize {
+        return match self.enable {
+            true => self.actuator_delay,
+            false => 0,
+        };
+    }
+}
+impl Default for PerturbationConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            seed: 0,
+            start_delay: 3.0,
+            interval: 4.0,
+            interval_jitter: 1.0,
+            max_impulse: 200.0,
+            sensor_noise: 0.0,
+            actuator_delay: 0,
+            recovery_time: 2.0,
+            robustness_weight: 0.0,
+        }
+    }
+}
+
+struct Push {
+    time: f32,
+    impulse: Vec2,
+}
+
+// Push schedule and sensor noise for the running trial, shared by every organism
+#[derive(Resource)]
+pub struct Perturbations {
+    pushes: Vec<Push>,
+    next_push: usize,
+    rng: StdRng,
+    last_elapsed: f32,
+    // Noise added to each sensor this tick
+    pub sensor_noise: Vec<f32>,
+    // Push times with each organism's position at the push
+    windows: Vec<(f32, Vec<f32>)>,
+    // Distance each organism travelled during finished recovery windows
+    recovery: Vec<f32>,
+}
+impl Perturbations {
+    pub fn new() -> Self {
+        return Self {
+            pushes: vec![],
+            next_push: 0,
+            rng: StdRng::seed_from_u64(0),
+            last_elapsed: 0.0,
+            sensor_noise: vec![],
+            windows: vec![],
+            recovery: vec![],
+        };
+    }
+
+    // Build the push schedule for a trial, the schedule only depends on the seed and generation
+    fn reset(&mut self, pc: &PerturbationConfig, gc: &GenerationConfig, num_organisms: usize) {
+        let seed = pc.seed.wrapping_add(gc.cur_generation as u64);
+        self.rng = StdRng::seed_from_u64(seed);
+        self.pushes.clear();
+        self.next_push = 0;
+        self.windows.clear();
+        self.recovery = vec![0.0; num_organisms];
+
+        let mut time = pc.start_delay;
+        while time < gc.generation_duration {
+            let angle = self.rng.gen_range(0.0..std::f32::consts::TAU);
+            let magnitude = self.rng.gen_range(0.0..=pc.max_impulse);
+            self.pushes.push(Push {
+                time,
+                impulse: vec2(angle.cos(), angle.sin()) * magnitude,
+            });
+            time += pc.interval.max(0.1) + self.rng.gen_range(-1.0..=1.0) * pc.interval_jitter;
+            time = time.max(self.pushes.last().unwrap().time + 0.1);
+        }
+    }
+
+    // Distance each spawned organism travelled while recovering from pushes
+    pub fn get_recovery(&self, num_organisms: usize) -> Vec<f32> {
+        let mut recovery = self.recovery.clone();
+        recovery.resize(num_organisms, 0.0);
+        return recovery;
+    }
+}
+
+fn get_centre_x(
+    o: &Organism,
+    joints: &Query<(&Transform, &mut ExternalImpulse), With<Joint>>,
+) -> f32 {
+    return o
+        .joints
+        .iter()
+        .filter_map(|j| joints.get(*j).ok())
+        .map(|(t, _)| t.translation.x)
+        .sum::<f32>()
+        / o.joints.len().max(1) as f32;
+}
+
+// Push organisms, refresh sensor noise and measure recovery
+pub fn apply_perturbations(
+    ol: Res<OrganismList>,
+    gc: Res<GenerationConfig>,
+    mut perturbations: ResMut<Perturbations>,
+    mut joints: Query<(&Transform, &mut ExternalImpulse), With<Joint>>,
+) {
+    let p = &mut *perturbations;
+    let pc = &gc.perturbation;
+    if !pc.enable || !ol.is_spawned {
+        return;
+    }
+
+    // The timer resets whenever a new trial or generation is spawned
+    let elapsed_secs = gc.timer.elapsed_secs();
+    if elapsed_secs < p.last_elapsed || p.recovery.len() != ol.organisms.len() {
+        p.reset(pc, &gc, ol.organisms.len());
+    }
+    p.last_elapsed = elapsed_secs;
+
+    // Same noise for every organism so no organism gets luckier readings
+    let noise_len = ol
+        .organisms
+        .iter()
+        .map(|o| o.brain.get_num_inputs())
+        .max()
+        .unwrap_or(0);
+    p.sensor_noise = match pc.sensor_noise > 0.0 {
+        true => (0..noise_len)
+            .map(|_| p.rng.gen_range(-pc.sensor_noise..pc.sensor_noise))
+            .collect(),
+        false => vec![],
+    };
+
+    // Finish recovery windows
+    while !p.windows.is_empty() && elapsed_secs >= p.windows[0].0 + pc.recovery_time {
+        let (_, start_x) = p.windows.remove(0);
+        for (i, o) in ol.organisms.iter().enumerate() {
+            p.recovery[i] += get_centre_x(o, &joints) - start_x[i];
+        }
+    }
+
+    while p.next_push < p.pushes.len() && elapsed_secs >= p.pushes[p.next_push].time {
+        let push_time = p.pushes[p.next_push].time;
+        let impulse = p.pushes[p.next_push].impulse;
+        p.next_push += 1;
+
+        let start_x = ol
+            .organisms
+            .iter()
+            .map(|o| get_centre_x(o, &joints))
+            .collect();
+        p.windows.push((push_time, start_x));
+
+        for o in ol.organisms.iter() {
+            let joint_impulse = impulse / o.joints.len().max(1) as f32;
+            for j in o.joints.iter() {
+                if let Ok((_, mut ei)) = joints.get_mut(*j) {
+                    ei.impulse += joint_impulse;
+                }
+            }
+        }
+    }
+}
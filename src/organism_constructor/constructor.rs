@@ -9,42 +9,62 @@ use bevy::{
 use bevy_rapier2d::prelude::{QueryFilter, QueryFilterFlags, RapierContext};
 
 use crate::{
-    controls::control_state::ControlState, handles::Handles, organism::organism::OrganismBuilder,
+    controls::control_state::ControlState,
+    handles::Handles,
+    organism::{bone::JointType, library::Blueprint, organism::OrganismBuilder},
 };
 
 use super::{
     construction_mode::{ConstructionMode, Mode},
     icons::{
         Anchor, AnchorPoint, AnchorSet, BoneIcon, BoneIconBundle, JointIcon, JointIconBundle,
-        MuscleIcon, MuscleIconBundle,
+        MotorIcon, MotorIconBundle, MuscleIcon, MuscleIconBundle,
     },
+    joint_settings::{spawn_joint_type_label, BoneJointSettings},
     mode_menu::ModeMenuBundle,
+    motor_settings::{spawn_motor_limit_label, MotorSettings},
 };
 
 #[derive(Resource)]
 pub struct Constructor {
     part_menu: Option<Entity>,
+    joint_type_label: Option<Entity>,
+    motor_limit_label: Option<Entity>,
     joints: Vec<Entity>,
     bones: Vec<Entity>,
     muscles: Vec<Entity>,
+    motors: Vec<Entity>,
 }
 impl Default for Constructor {
     fn default() -> Self {
         return Self {
             part_menu: None,
+            joint_type_label: None,
+            motor_limit_label: None,
             joints: vec![],
             bones: vec![],
             muscles: vec![],
+            motors: vec![],
         };
     }
 }
 impl Constructor {
-    pub fn spawn(&mut self, commands: &mut Commands) {
+    pub fn spawn(&mut self, commands: &mut Commands, bjs: &BoneJointSettings, ms: &MotorSettings) {
         self.part_menu = Some(ModeMenuBundle::new(commands));
+        self.joint_type_label = Some(spawn_joint_type_label(commands, bjs));
+        self.motor_limit_label = Some(spawn_motor_limit_label(commands, ms));
     }
     pub fn despawn(&mut self, commands: &mut Commands) {
         commands.entity(self.part_menu.unwrap()).despawn_recursive();
         self.part_menu = None;
+        if let Some(e) = self.joint_type_label {
+            commands.entity(e).despawn_recursive();
+            self.joint_type_label = None;
+        }
+        if let Some(e) = self.motor_limit_label {
+            commands.entity(e).despawn_recursive();
+            self.motor_limit_label = None;
+        }
         for e in self.joints.iter() {
             commands.entity(*e).despawn_recursive();
         }
@@ -54,6 +74,72 @@ impl Constructor {
         for e in self.muscles.iter() {
             commands.entity(*e).despawn();
         }
+        for e in self.motors.iter() {
+            commands.entity(*e).despawn();
+        }
+    }
+
+    // Spawn icons for every part of a blueprint so it can be edited
+    pub fn load_blueprint(&mut self, commands: &mut Commands, handles: &Handles, bp: &Blueprint) {
+        let mut joint_anchors = Vec::with_capacity(bp.joints.len());
+        for (i, pos) in bp.joints.iter().enumerate() {
+            let (joint, anchor) = JointIconBundle::new(
+                commands,
+                i,
+                *pos,
+                5.0,
+                &handles.joint_mesh,
+                &handles.joint_material,
+            );
+            self.joints.push(joint);
+            joint_anchors.push(anchor);
+        }
+
+        let mut bone_anchors = Vec::with_capacity(bp.bones.len());
+        for (i, [a, b]) in bp.bones.iter().enumerate() {
+            let joint_types = match bp.connections.get(i) {
+                Some(c) => *c,
+                None => [JointType::Revolute; 2],
+            };
+            let (bone, anchor) = BoneIconBundle::new(
+                commands,
+                i,
+                3.0,
+                &handles.bone_mesh,
+                &handles.bone_material,
+                [
+                    Anchor::Ent(joint_anchors[*a]),
+                    Anchor::Ent(joint_anchors[*b]),
+                ],
+                joint_types,
+            );
+            self.bones.push(bone);
+            bone_anchors.push(anchor);
+        }
+
+        for (i, [a, b]) in bp.muscles.iter().enumerate() {
+            let muscle = MuscleIconBundle::new(
+                commands,
+                i,
+                3.0,
+                &handles.muscle_mesh,
+                &handles.muscle_neutral_material,
+                [Anchor::Ent(bone_anchors[*a]), Anchor::Ent(bone_anchors[*b])],
+            );
+            self.muscles.push(muscle);
+        }
+        for (i, [a, b]) in bp.motors.iter().enumerate() {
+            let motor = MotorIconBundle::new(
+                commands,
+                i,
+                3.0,
+                &handles.muscle_mesh,
+                &handles.motor_material,
+                [Anchor::Ent(bone_anchors[*a]), Anchor::Ent(bone_anchors[*b])],
+                bp.motor_params.get(i).and_then(|p| p.limits),
+            );
+            self.motors.push(motor);
+        }
     }
 
     pub fn create_builder(
@@ -62,10 +148,14 @@ impl Constructor {
         anchors: &Query<&Parent, With<AnchorPoint>>,
         bone_anchors: &Query<(&AnchorSet, &BoneIcon)>,
         muscle_anchors: &Query<(&AnchorSet, &MuscleIcon)>,
+        motor_anchors: &Query<(&AnchorSet, &MotorIcon)>,
     ) -> Result<OrganismBuilder, Error> {
         let mut joint_pos = vec![Vec2::ZERO; self.joints.len()];
         let mut bones = vec![[0, 0]; self.bones.len()];
+        let mut connections = vec![[JointType::Revolute; 2]; self.bones.len()];
         let mut muscles = vec![[0, 0]; self.muscles.len()];
+        let mut motors = vec![[0, 0]; self.motors.len()];
+        let mut motor_limits = vec![None; self.motors.len()];
 
         for (t, j_i) in joint_icons {
             joint_pos[j_i.id] = t.translation.truncate();
@@ -87,6 +177,7 @@ impl Constructor {
             bones[b_i.id] = joint_icons
                 .get_many(anchors.get_many(a_s.get_ents()?)?.map(|p| p.get()))?
                 .map(|(_, j_i)| j_i.id);
+            connections[b_i.id] = b_i.joint_types;
         }
 
         for (a_s, m_i) in muscle_anchors.iter() {
@@ -96,16 +187,27 @@ impl Constructor {
                 .map(|(_, b_i)| b_i.id);
         }
 
+        for (a_s, m_i) in motor_anchors.iter() {
+            motors[m_i.id] = bone_anchors
+                .get_many(anchors.get_many(a_s.get_ents()?)?.map(|p| p.get()))?
+                .map(|(_, b_i)| b_i.id);
+            motor_limits[m_i.id] = m_i.limits;
+        }
+
         println!("joints {:?}", joint_pos);
         println!("bones {:?}", bones);
         println!("muscles {:?}", muscles);
-        return Ok(OrganismBuilder::new(
+        let mut ob = OrganismBuilder::new(
             1,
             vec![6, 6, 6],
             joint_pos,
             bones,
+            connections,
             muscles,
-        ));
+            motors,
+        );
+        ob.set_motor_limits(motor_limits);
+        return Ok(ob);
     }
 }
 
@@ -120,7 +222,7 @@ pub fn handle_joint_construction(
     }
     cs.double_click = false;
 
-    let joint_ent = JointIconBundle::new(
+    let (joint_ent, _) = JointIconBundle::new(
         &mut commands,
         c.joints.len(),
         cs.world_mouse_pos,
@@ -165,11 +267,14 @@ pub fn handle_anchored_icon_construction(
     handles: Res<Handles>,
     rapier_context: Res<RapierContext>,
     mut aic: ResMut<AnchoredIconConstruction>,
+    bjs: Res<BoneJointSettings>,
+    ms: Res<MotorSettings>,
 ) {
     if !cs.left_mouse_up {
         return;
     }
     cs.left_mouse_up = false;
+    // Bones anchor to joints, muscles and motors anchor to bones
     let is_bone = cm.current_mode == Mode::Bone;
 
     let mut potential_anchor_ent = None;
@@ -208,28 +313,42 @@ pub fn handle_anchored_icon_construction(
     match aic.anchored_entity {
         Some(anchored_icon_ent) => match anchored_icons.get_mut(anchored_icon_ent) {
             Ok(mut anchor_set) => {
-                match is_bone {
-                    true => c.bones.push(anchored_icon_ent),
-                    false => c.muscles.push(anchored_icon_ent),
+                match cm.current_mode {
+                    Mode::Bone => c.bones.push(anchored_icon_ent),
+                    Mode::Motor => c.motors.push(anchored_icon_ent),
+                    _ => c.muscles.push(anchored_icon_ent),
                 }
                 anchor_set.set_anchor(anchor_ent);
                 aic.anchored_entity = None;
             }
             Err(_) => todo!(),
         },
-        None => match is_bone {
-            true => {
-                let bone_icon_ent = BoneIconBundle::new(
+        None => match cm.current_mode {
+            Mode::Bone => {
+                let (bone_icon_ent, _) = BoneIconBundle::new(
                     &mut commands,
                     c.bones.len(),
                     3.0,
                     &handles.bone_mesh,
                     &handles.bone_material,
                     [Anchor::Ent(anchor_ent), Anchor::Mouse],
+                    bjs.joint_types,
                 );
                 aic.anchored_entity = Some(bone_icon_ent);
             }
-            false => {
+            Mode::Motor => {
+                let motor_icon_ent = MotorIconBundle::new(
+                    &mut commands,
+                    c.motors.len(),
+                    3.0,
+                    &handles.muscle_mesh,
+                    &handles.motor_material,
+                    [Anchor::Ent(anchor_ent), Anchor::Mouse],
+                    ms.get_limits(),
+                );
+                aic.anchored_entity = Some(motor_icon_ent);
+            }
+            _ => {
                 let muscle_icon_ent = MuscleIconBundle::new(
                     &mut commands,
                     c.muscles.len(),
@@ -9,7 +9,10 @@ use bevy::{
 };
 use bevy_rapier2d::prelude::{Collider, Sensor};
 
-use crate::{controls::control_state::ControlState, organism::helper_fn::vec2_z_rot};
+use crate::{
+    controls::control_state::ControlState,
+    organism::{bone::JointType, helper_fn::vec2_z_rot},
+};
 
 #[derive(Component)]
 pub struct DraggableIcon;
@@ -97,6 +100,7 @@ pub struct JointIconBundle {
     sensor: Sensor,
 }
 impl JointIconBundle {
+    // Returns the icon and the anchor point bones attach to
     pub fn new(
         commands: &mut Commands,
         id: usize,
@@ -104,8 +108,9 @@ impl JointIconBundle {
         radius: f32,
         mesh: &Mesh2dHandle,
         material: &Handle<ColorMaterial>,
-    ) -> Entity {
-        return commands
+    ) -> (Entity, Entity) {
+        let anchor = commands.spawn(AnchorPoint).id();
+        let icon = commands
             .spawn(Self {
                 joint_icon: JointIcon::new(id),
                 draggable_icon: DraggableIcon,
@@ -122,10 +127,9 @@ impl JointIconBundle {
                 collider: Collider::ball(1.0),
                 sensor: Sensor,
             })
-            .with_children(|j| {
-                j.spawn(AnchorPoint);
-            })
+            .add_child(anchor)
             .id();
+        return (icon, anchor);
     }
 }
 
@@ -162,10 +166,12 @@ impl AnchoredIconBundle {
 #[derive(Component)]
 pub struct BoneIcon {
     pub id: usize,
+    // Joint types of the start and end of the bone
+    pub joint_types: [JointType; 2],
 }
 impl BoneIcon {
-    pub fn new(id: usize) -> Self {
-        return Self { id };
+    pub fn new(id: usize, joint_types: [JointType; 2]) -> Self {
+        return Self { id, joint_types };
     }
 }
 #[derive(Bundle)]
@@ -176,6 +182,7 @@ pub struct BoneIconBundle {
     sensor: Sensor,
 }
 impl BoneIconBundle {
+    // Returns the icon and the anchor point muscles and motors attach to
     pub fn new(
         commands: &mut Commands,
         id: usize,
@@ -183,18 +190,19 @@ impl BoneIconBundle {
         mesh: &Mesh2dHandle,
         material: &Handle<ColorMaterial>,
         anchors: [Anchor; 2],
-    ) -> Entity {
-        return commands
+        joint_types: [JointType; 2],
+    ) -> (Entity, Entity) {
+        let anchor = commands.spawn(AnchorPoint).id();
+        let icon = commands
             .spawn(Self {
-                bone_icon: BoneIcon::new(id),
+                bone_icon: BoneIcon::new(id, joint_types),
                 anchored_icon_bundle: AnchoredIconBundle::new(width, -0.1, mesh, material, anchors),
                 collider: Collider::cuboid(0.5, 0.4),
                 sensor: Sensor,
             })
-            .with_children(|bone| {
-                bone.spawn(AnchorPoint);
-            })
+            .add_child(anchor)
             .id();
+        return (icon, anchor);
     }
 }
 
@@ -230,6 +238,40 @@ impl MuscleIconBundle {
     }
 }
 
+#[derive(Component)]
+pub struct MotorIcon {
+    pub id: usize,
+    pub limits: Option<[f32; 2]>,
+}
+impl MotorIcon {
+    pub fn new(id: usize, limits: Option<[f32; 2]>) -> Self {
+        return Self { id, limits };
+    }
+}
+#[derive(Bundle)]
+pub struct MotorIconBundle {
+    motor_icon: MotorIcon,
+    anchored_icon_bundle: AnchoredIconBundle,
+}
+impl MotorIconBundle {
+    pub fn new(
+        commands: &mut Commands,
+        id: usize,
+        width: f32,
+        mesh: &Mesh2dHandle,
+        material: &Handle<ColorMaterial>,
+        anchors: [Anchor; 2],
+        limits: Option<[f32; 2]>,
+    ) -> Entity {
+        return commands
+            .spawn(Self {
+                motor_icon: MotorIcon::new(id, limits),
+                anchored_icon_bundle: AnchoredIconBundle::new(width, -0.2, mesh, material, anchors),
+            })
+            .id();
+    }
+}
+
 pub fn anchor_icons(
     mut anchored_icons: Query<(&mut Transform, &AnchorSet), Without<AnchorPoint>>,
     anchors: Query<&Parent, With<AnchorPoint>>,
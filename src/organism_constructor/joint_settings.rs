@@ -0,0 +1,132 @@
+use bevy::{
+    prelude::{default, Commands, Component, Entity, Query, ResMut, Resource, TextBundle, With},
+    text::{Text, TextStyle},
+    ui::{PositionType, Style, Val},
+};
+
+use crate::{color_palette, controls::control_state::ControlState, organism::bone::JointType};
+
+// Joint types used for the start and end of newly placed bones
+#[derive(Resource)]
+pub struct BoneJointSettings {
+    pub joint_types: [JointType; 2],
+    // End of the bone the keys change
+    end: usize,
+}
+impl Default for BoneJointSettings {
+    fn default() -> Self {
+        Self {
+            joint_types: [JointType::Revolute; 2],
+            end: 0,
+        }
+    }
+}
+impl BoneJointSettings {
+    fn cycle(&mut self) {
+        let joint_type = &mut self.joint_types[self.end];
+        *joint_type = match *joint_type {
+            JointType::Revolute => JointType::LimitedRevolute {
+                min: -0.5,
+                max: 0.5,
+            },
+            JointType::LimitedRevolute { .. } => JointType::Fixed,
+            JointType::Fixed => JointType::Prismatic {
+                min: -5.0,
+                max: 5.0,
+            },
+            JointType::Prismatic { .. } => JointType::Revolute,
+        };
+    }
+
+    // Grow or shrink the limits symmetrically
+    fn adjust_limits(&mut self, delta: f32) {
+        match &mut self.joint_types[self.end] {
+            JointType::LimitedRevolute { min, max } => {
+                *max = (*max + delta * 0.1).clamp(0.0, std::f32::consts::PI);
+                *min = -*max;
+            }
+            JointType::Prismatic { min, max } => {
+                *max = (*max + delta).max(0.0);
+                *min = -*max;
+            }
+            JointType::Revolute | JointType::Fixed => {}
+        }
+    }
+
+    fn get_label(&self) -> String {
+        let ends = ["Start", "End"];
+        let lines = self
+            .joint_types
+            .iter()
+            .zip(ends)
+            .map(|(joint_type, end)| {
+                let description = match *joint_type {
+                    JointType::Revolute => "revolute".to_string(),
+                    JointType::LimitedRevolute { min, max } => {
+                        format!("limited revolute {:.1} to {:.1} rad", min, max)
+                    }
+                    JointType::Fixed => "fixed".to_string(),
+                    JointType::Prismatic { min, max } => {
+                        format!("prismatic {:.0} to {:.0}", min, max)
+                    }
+                };
+                format!("{} joint: {}", end, description)
+            })
+            .collect::<Vec<String>>();
+        return format!(
+            "{}\n{}\nEditing the {} (J to cycle, K to switch end, Q/E limits)",
+            lines[0],
+            lines[1],
+            ends[self.end].to_lowercase()
+        );
+    }
+}
+
+#[derive(Component)]
+pub struct JointTypeLabel;
+
+pub fn spawn_joint_type_label(commands: &mut Commands, settings: &BoneJointSettings) -> Entity {
+    return commands
+        .spawn((
+            JointTypeLabel,
+            TextBundle::from_section(
+                settings.get_label(),
+                TextStyle {
+                    font_size: 24.0,
+                    color: color_palette::TERTIARY,
+                    ..default()
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(10.0),
+                bottom: Val::Px(10.0),
+                ..default()
+            }),
+        ))
+        .id();
+}
+
+pub fn handle_joint_settings(
+    mut settings: ResMut<BoneJointSettings>,
+    mut cs: ResMut<ControlState>,
+    mut labels: Query<&mut Text, With<JointTypeLabel>>,
+) {
+    if !cs.cycle_joint_type && !cs.switch_joint_end && cs.limit_delta == 0.0 {
+        return;
+    }
+    if cs.switch_joint_end {
+        cs.switch_joint_end = false;
+        settings.end = 1 - settings.end;
+    }
+    if cs.cycle_joint_type {
+        cs.cycle_joint_type = false;
+        settings.cycle();
+    }
+    settings.adjust_limits(cs.limit_delta);
+    cs.limit_delta = 0.0;
+
+    for mut t in labels.iter_mut() {
+        t.sections[0].value = settings.get_label();
+    }
+}
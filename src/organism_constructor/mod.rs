@@ -3,9 +3,8 @@ use bevy::prelude::{
 };
 
 use crate::{
-    config::structs::GenerationConfig,
-    organism::organism_list::OrganismList,
-    scene_manager::{CurrentScene, Scene},
+    arena::ArenaConfig, config::structs::GenerationConfig, generation::start_run,
+    organism::organism_list::OrganismList, scene_manager::CurrentScene,
 };
 
 use self::{
@@ -15,7 +14,9 @@ use self::{
         Constructor,
     },
     drag::{move_dragging, set_draggable},
-    icons::{anchor_icons, AnchorPoint, AnchorSet, BoneIcon, JointIcon, MuscleIcon},
+    icons::{anchor_icons, AnchorPoint, AnchorSet, BoneIcon, JointIcon, MotorIcon, MuscleIcon},
+    joint_settings::{handle_joint_settings, BoneJointSettings},
+    motor_settings::{handle_motor_settings, MotorSettings},
 };
 
 mod construction_grid;
@@ -23,16 +24,22 @@ mod construction_mode;
 pub mod constructor;
 mod drag;
 mod icons;
+pub mod joint_settings;
 pub mod mode_menu;
+pub mod motor_settings;
 
 pub struct OrganismConstructionPlugin;
 impl Plugin for OrganismConstructionPlugin {
     fn build(&self, app: &mut bevy::prelude::App) {
         app.insert_resource(Constructor::default())
             .insert_resource(AnchoredIconConstruction::default())
+            .insert_resource(BoneJointSettings::default())
+            .insert_resource(MotorSettings::default())
             .add_plugins(ConstructionModePlugin)
             .add_systems(Update, (anchor_icons, move_dragging, set_draggable))
             .add_systems(Update, handle_joint_construction.run_if(construct_joint))
+            .add_systems(Update, handle_joint_settings.run_if(construct_bone))
+            .add_systems(Update, handle_motor_settings.run_if(construct_motor))
             .add_systems(
                 Update,
                 handle_anchored_icon_construction.run_if(construct_anchored_icon),
@@ -44,8 +51,16 @@ impl Plugin for OrganismConstructionPlugin {
 fn construct_joint(cm: Res<ConstructionMode>) -> bool {
     return cm.current_mode == Mode::Joint;
 }
+fn construct_bone(cm: Res<ConstructionMode>) -> bool {
+    return cm.current_mode == Mode::Bone;
+}
+fn construct_motor(cm: Res<ConstructionMode>) -> bool {
+    return cm.current_mode == Mode::Motor;
+}
 fn construct_anchored_icon(cm: Res<ConstructionMode>) -> bool {
-    return cm.current_mode == Mode::Bone || cm.current_mode == Mode::Muscle;
+    return cm.current_mode == Mode::Bone
+        || cm.current_mode == Mode::Muscle
+        || cm.current_mode == Mode::Motor;
 }
 
 fn construct_organism(cm: Res<ConstructionMode>) -> bool {
@@ -55,6 +70,7 @@ fn construct_organism(cm: Res<ConstructionMode>) -> bool {
 fn construct(
     c: Res<Constructor>,
     gc: Res<GenerationConfig>,
+    ac: Res<ArenaConfig>,
     mut ol: ResMut<OrganismList>,
     mut cs: ResMut<CurrentScene>,
     mut cm: ResMut<ConstructionMode>,
@@ -62,13 +78,17 @@ fn construct(
     anchors: Query<&Parent, With<AnchorPoint>>,
     bone_anchors: Query<(&AnchorSet, &BoneIcon)>,
     muscle_anchors: Query<(&AnchorSet, &MuscleIcon)>,
+    motor_anchors: Query<(&AnchorSet, &MotorIcon)>,
 ) {
     cm.current_mode = Mode::None;
-    match c.create_builder(&joint_icons, &anchors, &bone_anchors, &muscle_anchors) {
-        Ok(ob) => {
-            ol.builders = vec![ob; gc.num_organisms];
-            cs.next_scene = Scene::OrganismSimulation;
-        }
+    match c.create_builder(
+        &joint_icons,
+        &anchors,
+        &bone_anchors,
+        &muscle_anchors,
+        &motor_anchors,
+    ) {
+        Ok(ob) => start_run(ob, &gc, &ac, &mut ol, &mut cs),
         Err(e) => println!("Couldn't create organism {:?}", e),
     };
 }
@@ -0,0 +1,93 @@
+use bevy::{
+    prelude::{default, Commands, Component, Entity, Query, ResMut, Resource, TextBundle, With},
+    text::{Text, TextStyle},
+    ui::{PositionType, Style, Val},
+};
+
+use crate::{color_palette, controls::control_state::ControlState};
+
+// Angle limits used for newly placed motors
+#[derive(Resource)]
+pub struct MotorSettings {
+    // Limit either side of the spawn pose in radians, None lets the motor turn freely
+    pub limit: Option<f32>,
+}
+impl Default for MotorSettings {
+    fn default() -> Self {
+        Self { limit: None }
+    }
+}
+impl MotorSettings {
+    pub fn get_limits(&self) -> Option<[f32; 2]> {
+        return self.limit.map(|l| [-l, l]);
+    }
+
+    fn toggle(&mut self) {
+        self.limit = match self.limit {
+            Some(_) => None,
+            None => Some(0.5),
+        };
+    }
+
+    fn adjust_limit(&mut self, delta: f32) {
+        if let Some(limit) = &mut self.limit {
+            *limit = (*limit + delta * 0.1).clamp(0.0, std::f32::consts::PI);
+        }
+    }
+
+    fn get_label(&self) -> String {
+        return match self.limit {
+            Some(l) => format!(
+                "Motor limits: {:.1} to {:.1} rad (J to toggle, Q/E limits)",
+                -l, l
+            ),
+            None => "Motor limits: none (J to toggle)".to_string(),
+        };
+    }
+}
+
+#[derive(Component)]
+pub struct MotorLimitLabel;
+
+pub fn spawn_motor_limit_label(commands: &mut Commands, settings: &MotorSettings) -> Entity {
+    return commands
+        .spawn((
+            MotorLimitLabel,
+            TextBundle::from_section(
+                settings.get_label(),
+                TextStyle {
+                    font_size: 24.0,
+                    color: color_palette::TERTIARY,
+                    ..default()
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                right: Val::Px(10.0),
+                bottom: Val::Px(10.0),
+                ..default()
+            }),
+        ))
+        .id();
+}
+
+// Uses the joint type keys while placing motors
+pub fn handle_motor_settings(
+    mut settings: ResMut<MotorSettings>,
+    mut cs: ResMut<ControlState>,
+    mut labels: Query<&mut Text, With<MotorLimitLabel>>,
+) {
+    if !cs.cycle_joint_type && cs.limit_delta == 0.0 {
+        return;
+    }
+    if cs.cycle_joint_type {
+        cs.cycle_joint_type = false;
+        settings.toggle();
+    }
+    settings.adjust_limit(cs.limit_delta);
+    cs.limit_delta = 0.0;
+
+    for mut t in labels.iter_mut() {
+        t.sections[0].value = settings.get_label();
+    }
+}
@@ -0,0 +1,473 @@
+use bevy::{
+    asset::Error,
+    prelude::{ClearColor, Color, Vec2},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Write as _,
+    fs::{self, File},
+    io::BufWriter,
+    path::Path,
+};
+
+use crate::color_palette;
+
+use super::recording::{get_muscle_color, Outline, Pose, Recording};
+
+// Sizes of the parts in world units, the same as when they're spawned
+const JOINT_RADIUS: f32 = 5.0;
+const BONE_WIDTH: f32 = 3.0;
+const MUSCLE_WIDTH: f32 = 2.0;
+// Width terrain lines are drawn with
+const TERRAIN_WIDTH: f32 = 4.0;
+// Space left around the organisms when the viewport is fitted to them
+const VIEWPORT_PADDING: f32 = 50.0;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ExportConfig {
+    pub fps: f32,
+    // Size of the image in pixels
+    pub width: u32,
+    pub height: u32,
+    // Part of the world shown as [min x, min y, max x, max y], fitted to every position the
+    // organisms reach when not set
+    pub viewport: Option<[f32; 4]>,
+}
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self {
+            fps: 30.0,
+            width: 1280,
+            height: 720,
+            viewport: None,
+        }
+    }
+}
+
+// Maps world positions to pixels, y points down in images
+struct Viewport {
+    min: Vec2,
+    max: Vec2,
+    scale: Vec2,
+}
+impl Viewport {
+    fn new(config: &ExportConfig, recording: &Recording) -> Self {
+        let size = Vec2::new(config.width as f32, config.height as f32);
+        let (min, max) = match config.viewport {
+            Some([x0, y0, x1, y1]) => (Vec2::new(x0, y0), Vec2::new(x1, y1)),
+            None => {
+                // Grow the short side so the world isn't stretched
+                let (min, max) = recording.get_bounds();
+                let (min, max) = (min - VIEWPORT_PADDING, max + VIEWPORT_PADDING);
+                let scale = (size / (max - min)).min_element();
+                let half = size / scale / 2.0;
+                let center = (min + max) / 2.0;
+                (center - half, center + half)
+            }
+        };
+        return Self {
+            min,
+            max,
+            scale: size / (max - min),
+        };
+    }
+
+    fn to_pixel(&self, p: Vec2) -> Vec2 {
+        return Vec2::new(
+            (p.x - self.min.x) * self.scale.x,
+            (self.max.y - p.y) * self.scale.y,
+        );
+    }
+
+    fn to_pixels(&self, len: f32) -> f32 {
+        return len * self.scale.x;
+    }
+}
+
+// Times of the exported frames
+fn get_frame_times(config: &ExportConfig, recording: &Recording) -> Vec<f32> {
+    let num_frames = (recording.get_duration() * config.fps).floor() as usize + 1;
+    return (0..num_frames).map(|i| i as f32 / config.fps).collect();
+}
+
+// Write an animated SVG when the output ends in .svg, numbered PNG frames in the output
+// folder otherwise
+pub fn export(recording: &Recording, config: &ExportConfig, output: &str) -> Result<(), Error> {
+    if config.fps <= 0.0 || config.width == 0 || config.height == 0 {
+        return Err(Error::msg("Export fps, width and height must be above 0"));
+    }
+    if recording.times.is_empty() || recording.organisms.is_empty() {
+        return Err(Error::msg("Recording is empty"));
+    }
+    if output.ends_with(".svg") {
+        return fs::write(output, get_svg(recording, config)).map_err(Error::from);
+    }
+    return write_frames(recording, config, output);
+}
+
+// Every part is drawn once and its positions animated with SMIL so the file plays in a
+// browser without any scripts
+fn get_svg(recording: &Recording, config: &ExportConfig) -> String {
+    let view = Viewport::new(config, recording);
+    let times = get_frame_times(config, recording);
+    let frames = times
+        .iter()
+        .map(|t| recording.get_poses(*t))
+        .collect::<Vec<Vec<Pose>>>();
+    let duration = times.len() as f32 / config.fps;
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{1}\" \
+         viewBox=\"0 0 {0} {1}\">",
+        config.width, config.height
+    );
+    let _ = writeln!(
+        svg,
+        "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>",
+        get_svg_color(ClearColor::default().0)
+    );
+
+    // Values of one attribute in every frame
+    let animate = |svg: &mut String, name: &str, values: Vec<String>, discrete: bool| {
+        let mode = match discrete {
+            true => " calcMode=\"discrete\"",
+            false => "",
+        };
+        let _ = writeln!(
+            svg,
+            "  <animate attributeName=\"{}\" dur=\"{}s\" repeatCount=\"indefinite\"{} \
+             values=\"{}\"/>",
+            name,
+            duration,
+            mode,
+            values.join(";")
+        );
+    };
+    let coord = |p: Option<Vec2>, x: bool| {
+        let p = view.to_pixel(p.unwrap_or_default());
+        return match x {
+            true => format!("{:.1}", p.x),
+            false => format!("{:.1}", p.y),
+        };
+    };
+
+    // Environment shapes only get animated when they move
+    let outlines = times
+        .iter()
+        .map(|t| recording.get_outlines(*t))
+        .collect::<Vec<_>>();
+    let env_color = get_svg_color(color_palette::ENVIRONMENT);
+    for (k, first) in outlines.first().into_iter().flatten().enumerate() {
+        let moving = outlines.iter().any(|f| f[k] != *first);
+        match first {
+            Outline::Polygon(points) | Outline::Line(points) => {
+                let (tag, style) = match first {
+                    Outline::Polygon(_) => ("polygon", format!("fill=\"{}\"", env_color)),
+                    _ => (
+                        "polyline",
+                        format!(
+                            "fill=\"none\" stroke=\"{}\" stroke-width=\"{:.1}\" \
+                             stroke-linejoin=\"round\"",
+                            env_color,
+                            view.to_pixels(TERRAIN_WIDTH)
+                        ),
+                    ),
+                };
+                let _ = write!(
+                    svg,
+                    "<{} {} points=\"{}\"",
+                    tag,
+                    style,
+                    get_svg_points(&view, points)
+                );
+                if !moving {
+                    svg += "/>\n";
+                    continue;
+                }
+                svg += ">\n";
+                let values = outlines
+                    .iter()
+                    .map(|f| match &f[k] {
+                        Outline::Polygon(p) | Outline::Line(p) => get_svg_points(&view, p),
+                        Outline::Circle(..) => String::new(),
+                    })
+                    .collect();
+                animate(&mut svg, "points", values, false);
+                let _ = writeln!(svg, "</{}>", tag);
+            }
+            Outline::Circle(center, radius) => {
+                let _ = write!(
+                    svg,
+                    "<circle r=\"{:.1}\" fill=\"{}\"",
+                    view.to_pixels(*radius),
+                    env_color
+                );
+                if !moving {
+                    let center = view.to_pixel(*center);
+                    let _ = writeln!(svg, " cx=\"{:.1}\" cy=\"{:.1}\"/>", center.x, center.y);
+                    continue;
+                }
+                svg += ">\n";
+                let centers = outlines
+                    .iter()
+                    .map(|f| match &f[k] {
+                        Outline::Circle(c, _) => Some(*c),
+                        _ => None,
+                    })
+                    .collect::<Vec<Option<Vec2>>>();
+                animate(
+                    &mut svg,
+                    "cx",
+                    centers.iter().map(|c| coord(*c, true)).collect(),
+                    false,
+                );
+                animate(
+                    &mut svg,
+                    "cy",
+                    centers.iter().map(|c| coord(*c, false)).collect(),
+                    false,
+                );
+                svg += "</circle>\n";
+            }
+        }
+    }
+
+    for (i, o) in recording.organisms.iter().enumerate() {
+        let poses = frames
+            .iter()
+            .map(|f| f.get(i))
+            .collect::<Vec<Option<&Pose>>>();
+        let joint = |j: usize, x: bool| -> Vec<String> {
+            return poses
+                .iter()
+                .map(|p| coord(p.and_then(|p| p.joints.get(j).copied()), x))
+                .collect();
+        };
+
+        let _ = writeln!(svg, "<g id=\"organism_{}\">", o.id);
+        let num_muscles = o.muscles.first().map_or(0, |m| m.len());
+        for m in 0..num_muscles {
+            let end = |e: usize, x: bool| -> Vec<String> {
+                return poses
+                    .iter()
+                    .map(|p| coord(p.and_then(|p| p.muscles.get(m)).map(|m| m[e]), x))
+                    .collect();
+            };
+            let colors = poses
+                .iter()
+                .map(|p| {
+                    let act = p.and_then(|p| p.activations.get(m).copied()).unwrap_or(0.0);
+                    get_svg_color(get_muscle_color(act))
+                })
+                .collect();
+            let _ = writeln!(
+                svg,
+                "<line stroke-width=\"{:.1}\" stroke-linecap=\"round\">",
+                view.to_pixels(MUSCLE_WIDTH)
+            );
+            animate(&mut svg, "x1", end(0, true), false);
+            animate(&mut svg, "y1", end(0, false), false);
+            animate(&mut svg, "x2", end(1, true), false);
+            animate(&mut svg, "y2", end(1, false), false);
+            animate(&mut svg, "stroke", colors, true);
+            svg += "</line>\n";
+        }
+        for [a, b] in o.bones.iter() {
+            let _ = writeln!(
+                svg,
+                "<line stroke=\"{}\" stroke-width=\"{:.1}\" stroke-linecap=\"round\">",
+                get_svg_color(color_palette::BONE),
+                view.to_pixels(BONE_WIDTH)
+            );
+            animate(&mut svg, "x1", joint(*a, true), false);
+            animate(&mut svg, "y1", joint(*a, false), false);
+            animate(&mut svg, "x2", joint(*b, true), false);
+            animate(&mut svg, "y2", joint(*b, false), false);
+            svg += "</line>\n";
+        }
+        let num_joints = o.joints.first().map_or(0, |j| j.len());
+        for j in 0..num_joints {
+            let _ = writeln!(
+                svg,
+                "<circle r=\"{:.1}\" fill=\"{}\">",
+                view.to_pixels(JOINT_RADIUS),
+                get_svg_color(color_palette::JOINT)
+            );
+            animate(&mut svg, "cx", joint(j, true), false);
+            animate(&mut svg, "cy", joint(j, false), false);
+            svg += "</circle>\n";
+        }
+        svg += "</g>\n";
+    }
+    svg += "</svg>\n";
+    return svg;
+}
+
+fn get_svg_points(view: &Viewport, points: &[Vec2]) -> String {
+    return points
+        .iter()
+        .map(|p| {
+            let p = view.to_pixel(*p);
+            format!("{:.1},{:.1}", p.x, p.y)
+        })
+        .collect::<Vec<String>>()
+        .join(" ");
+}
+
+fn get_svg_color(color: Color) -> String {
+    let [r, g, b, _] = color.as_rgba_u8();
+    return format!("#{:02x}{:02x}{:02x}", r, g, b);
+}
+
+fn write_frames(recording: &Recording, config: &ExportConfig, folder: &str) -> Result<(), Error> {
+    fs::create_dir_all(folder)?;
+    let view = Viewport::new(config, recording);
+    let background = ClearColor::default().0;
+
+    for (i, t) in get_frame_times(config, recording).iter().enumerate() {
+        let mut canvas = Canvas::new(config.width, config.height, background);
+        for outline in recording.get_outlines(*t) {
+            match outline {
+                Outline::Polygon(points) => {
+                    let points = points.iter().map(|p| view.to_pixel(*p)).collect::<Vec<_>>();
+                    canvas.draw_polygon(&points, color_palette::ENVIRONMENT);
+                }
+                Outline::Line(points) => {
+                    let width = view.to_pixels(TERRAIN_WIDTH);
+                    for (a, b) in points.iter().zip(points.iter().skip(1)) {
+                        let (a, b) = (view.to_pixel(*a), view.to_pixel(*b));
+                        canvas.draw_line(a, b, width, color_palette::ENVIRONMENT);
+                    }
+                }
+                Outline::Circle(center, radius) => {
+                    let radius = view.to_pixels(radius);
+                    canvas.draw_circle(view.to_pixel(center), radius, color_palette::ENVIRONMENT);
+                }
+            }
+        }
+        for (o, pose) in recording.organisms.iter().zip(recording.get_poses(*t)) {
+            for ([a, b], act) in pose.muscles.iter().zip(pose.activations.iter()) {
+                let width = view.to_pixels(MUSCLE_WIDTH);
+                let color = get_muscle_color(*act);
+                canvas.draw_line(view.to_pixel(*a), view.to_pixel(*b), width, color);
+            }
+            for [a, b] in o.bones.iter() {
+                if let (Some(a), Some(b)) = (pose.joints.get(*a), pose.joints.get(*b)) {
+                    let width = view.to_pixels(BONE_WIDTH);
+                    let (a, b) = (view.to_pixel(*a), view.to_pixel(*b));
+                    canvas.draw_line(a, b, width, color_palette::BONE);
+                }
+            }
+            for j in pose.joints.iter() {
+                let radius = view.to_pixels(JOINT_RADIUS);
+                canvas.draw_circle(view.to_pixel(*j), radius, color_palette::JOINT);
+            }
+        }
+        canvas.save(&Path::new(folder).join(format!("frame_{:05}.png", i)))?;
+    }
+    return Ok(());
+}
+
+// RGBA image drawn on without a GPU, edges are antialiased by how far each pixel is from
+// the shape
+struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<[f32; 4]>,
+}
+impl Canvas {
+    fn new(width: u32, height: u32, background: Color) -> Self {
+        return Self {
+            width,
+            height,
+            pixels: vec![background.as_rgba_f32(); (width * height) as usize],
+        };
+    }
+
+    // Blend a color over every pixel close enough to a shape, distance is from a pixel's
+    // center to the shape's edge and negative inside it
+    fn fill(&mut self, min: Vec2, max: Vec2, color: Color, distance: impl Fn(Vec2) -> f32) {
+        let color = color.as_rgba_f32();
+        let x0 = min.x.floor().max(0.0) as u32;
+        let y0 = min.y.floor().max(0.0) as u32;
+        let x1 = (max.x.ceil().max(0.0) as u32).min(self.width);
+        let y1 = (max.y.ceil().max(0.0) as u32).min(self.height);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let d = distance(Vec2::new(x as f32 + 0.5, y as f32 + 0.5));
+                let coverage = (0.5 - d).clamp(0.0, 1.0) * color[3];
+                if coverage <= 0.0 {
+                    continue;
+                }
+                let p = &mut self.pixels[(y * self.width + x) as usize];
+                let alpha = p[3];
+                for (p, c) in p.iter_mut().zip(color).take(3) {
+                    *p += (c - *p) * coverage;
+                }
+                p[3] = alpha + (1.0 - alpha) * coverage;
+            }
+        }
+    }
+
+    fn draw_circle(&mut self, center: Vec2, radius: f32, color: Color) {
+        let extent = Vec2::splat(radius + 1.0);
+        self.fill(center - extent, center + extent, color, |p| {
+            p.distance(center) - radius
+        });
+    }
+
+    fn draw_line(&mut self, a: Vec2, b: Vec2, width: f32, color: Color) {
+        let half = width.max(1.0) / 2.0;
+        let extent = Vec2::splat(half + 1.0);
+        let ab = b - a;
+        let len_sq = ab.length_squared().max(f32::EPSILON);
+        self.fill(a.min(b) - extent, a.max(b) + extent, color, |p| {
+            let t = ((p - a).dot(ab) / len_sq).clamp(0.0, 1.0);
+            return p.distance(a + ab * t) - half;
+        });
+    }
+
+    // Shapes are small so every edge is checked for every pixel, inside is found by counting
+    // the edges crossed going right
+    fn draw_polygon(&mut self, points: &[Vec2], color: Color) {
+        if points.len() < 3 {
+            return;
+        }
+        let min = points.iter().fold(Vec2::splat(f32::MAX), |m, p| m.min(*p));
+        let max = points.iter().fold(Vec2::splat(f32::MIN), |m, p| m.max(*p));
+        self.fill(min - 1.0, max + 1.0, color, |p| {
+            let mut dist = f32::MAX;
+            let mut inside = false;
+            for (a, b) in points.iter().zip(points.iter().cycle().skip(1)) {
+                let ab = *b - *a;
+                let t = ((p - *a).dot(ab) / ab.length_squared().max(f32::EPSILON)).clamp(0.0, 1.0);
+                dist = dist.min(p.distance(*a + ab * t));
+                if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * ab.x {
+                    inside = !inside;
+                }
+            }
+            return match inside {
+                true => -dist,
+                false => dist,
+            };
+        });
+    }
+
+    fn save(&self, path: &Path) -> Result<(), Error> {
+        let data = self
+            .pixels
+            .iter()
+            .flat_map(|p| p.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8))
+            .collect::<Vec<u8>>();
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&data)?;
+        return Ok(());
+    }
+}
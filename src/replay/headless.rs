@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+use bevy::{
+    asset::{AddAsset, AssetPlugin, Error},
+    prelude::{
+        default, App, HierarchyPlugin, Mesh, MinimalPlugins, PreStartup, TransformPlugin, Vec2,
+    },
+    sprite::ColorMaterial,
+    time::TimeUpdateStrategy,
+};
+use bevy_rapier2d::prelude::{RapierConfiguration, TimestepMode};
+
+use crate::{
+    arena::ArenaPlugin,
+    collider_layer::ColliderLayerPlugin,
+    config::{structs::SaveConfig, ConfigPlugin},
+    controls::control_state::ControlState,
+    generation::GenerationPlugin,
+    handles::setup_handles,
+    organism::{library::OrganismLibrary, organism_list::OrganismList},
+    organism_constructor::{
+        constructor::Constructor, joint_settings::BoneJointSettings, motor_settings::MotorSettings,
+    },
+    scene_manager::SceneManagerPlugin,
+    start_menu::StartMenu,
+    task::TaskPlugin,
+};
+
+use super::{
+    recording::{Recorder, Recording},
+    ReplayConfig, ReplayPlugin,
+};
+
+// Simulated seconds per update, physics takes one step every update so runs don't depend on
+// how fast the machine is
+const FRAME_TIME: f32 = 1.0 / 60.0;
+// Give up when the generation hasn't been scored after this many updates
+const MAX_UPDATES: usize = 60 * 60 * 30;
+
+// Simulate the organisms of a save without a window or GPU and record them through the
+// first trial of their generation
+pub fn record_save(path: &str) -> Result<Recording, Error> {
+    let mut app = App::new();
+    app.insert_resource(RapierConfiguration {
+        // Same as the windowed app
+        gravity: Vec2::NEG_Y * 200.0,
+        timestep_mode: TimestepMode::Fixed {
+            dt: FRAME_TIME,
+            substeps: 1,
+        },
+        ..default()
+    })
+    .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+        FRAME_TIME,
+    )))
+    .add_plugins((
+        MinimalPlugins,
+        TransformPlugin,
+        HierarchyPlugin,
+        AssetPlugin::default(),
+    ))
+    .add_asset::<Mesh>()
+    .add_asset::<ColorMaterial>()
+    .add_systems(PreStartup, setup_handles)
+    // Scenes expect the menus and controls to exist even though they're never shown
+    .insert_resource(ControlState::default())
+    .insert_resource(StartMenu::default())
+    .insert_resource(Constructor::default())
+    .insert_resource(BoneJointSettings::default())
+    .insert_resource(MotorSettings::default())
+    .insert_resource(OrganismLibrary::default())
+    .add_plugins((
+        ColliderLayerPlugin,
+        ConfigPlugin,
+        SceneManagerPlugin,
+        GenerationPlugin,
+        TaskPlugin,
+        ArenaPlugin,
+        ReplayPlugin,
+    ));
+
+    // Run the save without writing anything and keep the recording in memory
+    let mut sc = app.world.resource_mut::<SaveConfig>();
+    sc.enable = false;
+    sc.load_save = true;
+    sc.load_file = path.to_string();
+    sc.import_file = None;
+    sc.autosave_on_exit = false;
+    sc.log_stats = false;
+    sc.log_lineage = false;
+    let mut rc = app.world.resource_mut::<ReplayConfig>();
+    rc.record_every = 1;
+    rc.file = None;
+    app.world.resource_mut::<Recorder>().keep = true;
+
+    app.finish();
+    app.cleanup();
+    app.update();
+    if app.world.resource::<OrganismList>().builders.is_empty() {
+        return Err(Error::msg(format!(
+            "No organisms could be loaded from {}",
+            path
+        )));
+    }
+    for _ in 0..MAX_UPDATES {
+        app.update();
+        if let Some(r) = app.world.resource_mut::<Recorder>().finished.take() {
+            return Ok(r);
+        }
+    }
+    return Err(Error::msg(format!(
+        "Generation didn't finish after {} updates",
+        MAX_UPDATES
+    )));
+}
@@ -0,0 +1,180 @@
+use bevy::{
+    prelude::{
+        App, Condition, Gizmos, IntoSystemConfigs, Plugin, Res, ResMut, Resource, Startup, Update,
+    },
+    time::Time,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arena::handle_arena_generation,
+    color_palette,
+    controls::control_state::ControlState,
+    generation::handle_generation,
+    scene_manager::{is_arena, is_replay, is_simulation, CurrentScene, Scene},
+};
+
+use self::{
+    export::ExportConfig,
+    recording::{get_muscle_color, record_trajectories, Outline, Recorder, Recording},
+};
+
+pub mod export;
+pub mod headless;
+pub mod recording;
+
+pub struct ReplayPlugin;
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Recorder::default())
+            .insert_resource(ReplayPlayer::default())
+            .add_systems(Startup, start_replay)
+            .add_systems(
+                Update,
+                record_trajectories
+                    .after(handle_generation)
+                    .after(handle_arena_generation)
+                    .run_if(is_simulation.or_else(is_arena)),
+            )
+            .add_systems(
+                Update,
+                (update_replay, draw_replay).chain().run_if(is_replay),
+            );
+    }
+}
+
+#[derive(Resource, Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ReplayConfig {
+    // Record organisms every this many generations, 0 to never record
+    pub record_every: u32,
+    // Only keep the best few organisms of a recorded generation, all of them when not set
+    pub record_best: Option<usize>,
+    // Recording to play back, the replay scene starts straight away when set
+    pub file: Option<String>,
+    pub export: ExportConfig,
+}
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        Self {
+            record_every: 0,
+            record_best: Some(5),
+            file: None,
+            export: ExportConfig::default(),
+        }
+    }
+}
+
+// Plays a recording back by drawing it, physics isn't involved
+#[derive(Resource)]
+pub struct ReplayPlayer {
+    recording: Option<Recording>,
+    time: f32,
+    speed: f32,
+    paused: bool,
+}
+impl Default for ReplayPlayer {
+    fn default() -> Self {
+        Self {
+            recording: None,
+            time: 0.0,
+            speed: 1.0,
+            paused: false,
+        }
+    }
+}
+impl ReplayPlayer {
+    pub fn load(&mut self, rc: &ReplayConfig) {
+        *self = Self::default();
+        let path = match &rc.file {
+            Some(p) => p,
+            None => {
+                println!("No recording to replay");
+                return;
+            }
+        };
+        match Recording::load(path) {
+            Ok(r) => {
+                println!("Replaying generation {} from {:?}", r.generation, path);
+                self.recording = Some(r);
+            }
+            Err(err) => println!("Error loading recording {:?}, {:?}", path, err),
+        }
+    }
+
+    pub fn unload(&mut self) {
+        self.recording = None;
+    }
+}
+
+fn start_replay(rc: Res<ReplayConfig>, mut cs: ResMut<CurrentScene>) {
+    if rc.file.is_some() {
+        cs.next_scene = Scene::Replay;
+    }
+}
+
+// Space pauses, left and right scrub and comma and period change the speed
+fn update_replay(time: Res<Time>, mut cs: ResMut<ControlState>, mut player: ResMut<ReplayPlayer>) {
+    let duration = match &player.recording {
+        Some(r) => r.get_duration(),
+        None => return,
+    };
+    let dt = time.delta_seconds();
+
+    if cs.toggle_pause {
+        cs.toggle_pause = false;
+        player.paused = !player.paused;
+    }
+    if cs.speed_delta != 0.0 {
+        player.speed = (player.speed * 2.0_f32.powf(cs.speed_delta)).clamp(0.125, 8.0);
+        cs.speed_delta = 0.0;
+    }
+
+    let mut t = player.time + cs.scrub_delta * dt * 4.0;
+    if !player.paused {
+        t += dt * player.speed;
+    }
+    // Loop back to the start
+    if t > duration {
+        t = 0.0;
+    }
+    player.time = t.clamp(0.0, duration);
+}
+
+fn draw_replay(mut gizmos: Gizmos, player: Res<ReplayPlayer>) {
+    let recording = match &player.recording {
+        Some(r) => r,
+        None => return,
+    };
+
+    for outline in recording.get_outlines(player.time) {
+        match outline {
+            Outline::Polygon(mut points) => {
+                // Close the shape
+                if let Some(first) = points.first().copied() {
+                    points.push(first);
+                }
+                gizmos.linestrip_2d(points, color_palette::ENVIRONMENT);
+            }
+            Outline::Line(points) => gizmos.linestrip_2d(points, color_palette::ENVIRONMENT),
+            Outline::Circle(center, radius) => {
+                gizmos.circle_2d(center, radius, color_palette::ENVIRONMENT);
+            }
+        }
+    }
+
+    let poses = recording.get_poses(player.time);
+    for (o, pose) in recording.organisms.iter().zip(poses.iter()) {
+        for [a, b] in o.bones.iter() {
+            if let (Some(a), Some(b)) = (pose.joints.get(*a), pose.joints.get(*b)) {
+                gizmos.line_2d(*a, *b, color_palette::BONE);
+            }
+        }
+        for j in pose.joints.iter() {
+            gizmos.circle_2d(*j, 5.0, color_palette::JOINT);
+        }
+        for ([a, b], act) in pose.muscles.iter().zip(pose.activations.iter()) {
+            gizmos.line_2d(*a, *b, get_muscle_color(*act));
+        }
+    }
+}
@@ -0,0 +1,369 @@
+use bevy::{
+    asset::Error,
+    math::vec2,
+    prelude::{Color, Query, Res, ResMut, Resource, Transform, Vec2, With, Without},
+};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::BufReader,
+    path::Path,
+};
+
+use crate::{
+    arena::Arena,
+    color_palette,
+    config::structs::{GenerationConfig, SaveConfig},
+    generation::{
+        env_description::{Shape, ShapeDescription},
+        environment::Environment,
+        stats::GenerationStats,
+        trial::Trials,
+    },
+    organism::{bone::Bone, joint::Joint, muscle::Muscle, organism_list::OrganismList},
+};
+
+use super::ReplayConfig;
+
+// Movement of one organism through a generation
+#[derive(Serialize, Deserialize, Default)]
+pub struct OrganismRecording {
+    pub id: u64,
+    pub score: Option<f32>,
+    // Offset of the lane the organism ran in, the environment is drawn at every kept lane
+    pub lane_offset: Vec2,
+    // Joints each bone connects
+    pub bones: Vec<[usize; 2]>,
+    // Joint positions, muscle end points and muscle activations of each frame
+    pub joints: Vec<Vec<Vec2>>,
+    pub muscles: Vec<Vec<[Vec2; 2]>>,
+    pub activations: Vec<Vec<f32>>,
+}
+
+// Where an organism's parts are at a time between frames
+pub struct Pose {
+    pub joints: Vec<Vec2>,
+    pub muscles: Vec<[Vec2; 2]>,
+    pub activations: Vec<f32>,
+}
+
+// Environment shape at a time, placed in a lane
+#[derive(PartialEq)]
+pub enum Outline {
+    Polygon(Vec<Vec2>),
+    // Open line through the points, used for terrain
+    Line(Vec<Vec2>),
+    Circle(Vec2, f32),
+}
+
+// Moving shapes are moved on from where they started at their constant velocity
+fn get_outline(s: &ShapeDescription, lane_offset: Vec2, time: f32) -> Outline {
+    let (linvel, angvel) = match s.motion {
+        Some(m) => (m.linvel, m.angvel),
+        None => (Vec2::ZERO, 0.0),
+    };
+    let translation = s.translation + lane_offset + linvel * time;
+    let rotation = Vec2::from_angle(s.rotation + angvel * time);
+    let place = |points: Vec<Vec2>| -> Vec<Vec2> {
+        return points
+            .into_iter()
+            .map(|p| translation + rotation.rotate(p))
+            .collect();
+    };
+    return match &s.shape {
+        Shape::Box { extents } => {
+            let h = *extents * 0.5;
+            Outline::Polygon(place(vec![-h, vec2(h.x, -h.y), h, vec2(-h.x, h.y)]))
+        }
+        Shape::Ramp { width, height } => Outline::Polygon(place(vec![
+            Vec2::ZERO,
+            vec2(*width, 0.0),
+            vec2(*width, *height),
+        ])),
+        Shape::Polyline { points } => Outline::Line(place(points.clone())),
+        Shape::Circle { radius } => Outline::Circle(translation, *radius),
+    };
+}
+
+// Muscles are coloured like the materials in Handles, negative activations shorten them
+pub fn get_muscle_color(activation: f32) -> Color {
+    if activation < -0.01 {
+        return color_palette::MUSCLE_CONTRACT;
+    } else if activation > 0.01 {
+        return color_palette::MUSCLE_EXPAND;
+    }
+    return color_palette::MUSCLE_NEUTRAL;
+}
+
+// Organisms recorded through the first trial of a generation, saved as gzipped bincode
+#[derive(Serialize, Deserialize, Default)]
+pub struct Recording {
+    pub generation: u32,
+    // Seconds into the trial of each frame
+    pub times: Vec<f32>,
+    pub organisms: Vec<OrganismRecording>,
+    // Shapes of the first trial's environment relative to a lane
+    pub environment: Vec<ShapeDescription>,
+}
+impl Recording {
+    pub fn get_duration(&self) -> f32 {
+        return self.times.last().copied().unwrap_or(0.0);
+    }
+
+    // Index of the frame before a time and how far it is to the next frame
+    pub fn get_frame(&self, time: f32) -> (usize, f32) {
+        let next = self.times.partition_point(|t| *t <= time);
+        if next == 0 || next >= self.times.len() {
+            return (next.min(self.times.len().max(1) - 1), 0.0);
+        }
+        let (a, b) = (self.times[next - 1], self.times[next]);
+        let t = match b - a > f32::EPSILON {
+            true => (time - a) / (b - a),
+            false => 0.0,
+        };
+        return (next - 1, t);
+    }
+
+    // Pose of every organism at a time, positions are interpolated between frames
+    pub fn get_poses(&self, time: f32) -> Vec<Pose> {
+        let (frame, t) = self.get_frame(time);
+        let next = (frame + 1).min(self.times.len().saturating_sub(1));
+
+        let mut poses = Vec::with_capacity(self.organisms.len());
+        for o in self.organisms.iter() {
+            let joints = match (o.joints.get(frame), o.joints.get(next)) {
+                (Some(a), Some(b)) => a.iter().zip(b).map(|(a, b)| a.lerp(*b, t)).collect(),
+                _ => vec![],
+            };
+            let muscles = match (o.muscles.get(frame), o.muscles.get(next)) {
+                (Some(a), Some(b)) => a
+                    .iter()
+                    .zip(b)
+                    .map(|(a, b)| [a[0].lerp(b[0], t), a[1].lerp(b[1], t)])
+                    .collect(),
+                _ => vec![],
+            };
+            poses.push(Pose {
+                joints,
+                muscles,
+                activations: o.activations.get(frame).cloned().unwrap_or_default(),
+            });
+        }
+        return poses;
+    }
+
+    // Environment of every lane a kept organism ran in, lanes are only drawn once when
+    // organisms shared them
+    pub fn get_outlines(&self, time: f32) -> Vec<Outline> {
+        let mut lane_offsets: Vec<Vec2> = vec![];
+        for o in self.organisms.iter() {
+            if !lane_offsets.contains(&o.lane_offset) {
+                lane_offsets.push(o.lane_offset);
+            }
+        }
+        return lane_offsets
+            .iter()
+            .flat_map(|l| self.environment.iter().map(|s| get_outline(s, *l, time)))
+            .collect();
+    }
+
+    // Smallest and largest position any joint reaches
+    pub fn get_bounds(&self) -> (Vec2, Vec2) {
+        let mut min = Vec2::splat(f32::MAX);
+        let mut max = Vec2::splat(f32::MIN);
+        for j in self
+            .organisms
+            .iter()
+            .flat_map(|o| o.joints.iter().flatten())
+        {
+            min = min.min(*j);
+            max = max.max(*j);
+        }
+        if min.x > max.x {
+            return (Vec2::ZERO, Vec2::ZERO);
+        }
+        return (min, max);
+    }
+
+    #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+    pub fn load(path: &str) -> Result<Self, Error> {
+        return Err(Error::msg(format!("Can't load {} on the web", path)));
+    }
+
+    #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+    pub fn load(path: &str) -> Result<Self, Error> {
+        let file = File::open(path)?;
+        let decoder = GzDecoder::new(BufReader::new(file));
+        return Ok(bincode::deserialize_from(decoder)?);
+    }
+
+    fn save(&self, sc: &SaveConfig) -> Result<String, Error> {
+        fs::create_dir_all(&sc.folder)?;
+        let time = chrono::offset::Local::now().format("%d-%m-%Y_%H-%M");
+        let path = Path::new(&sc.folder).join(format!("{}_gen{}.replay", time, self.generation));
+        let mut encoder = GzEncoder::new(File::create(&path)?, Compression::default());
+        bincode::serialize_into(&mut encoder, self)?;
+        encoder.finish()?;
+        return Ok(path.to_string_lossy().to_string());
+    }
+}
+
+// Recording of the running generation
+#[derive(Resource, Default)]
+pub struct Recorder {
+    recording: Option<Recording>,
+    // Keep finished recordings here instead of saving them, used when exporting
+    pub keep: bool,
+    pub finished: Option<Recording>,
+}
+impl Recorder {
+    // Keep the best organisms now the generation's scores are known and save them
+    fn finish(
+        &mut self,
+        rc: &ReplayConfig,
+        sc: &SaveConfig,
+        stats: &GenerationStats,
+        arena: &Arena,
+    ) {
+        let mut recording = match self.recording.take() {
+            Some(r) => r,
+            None => return,
+        };
+        if recording.times.is_empty() {
+            return;
+        }
+        let generation = recording.generation;
+        let scores = match stats
+            .history
+            .iter()
+            .rev()
+            .find(|r| r.generation == generation)
+        {
+            Some(r) => r.scores.clone(),
+            // The arena keeps its own scores, predators first like the organism list
+            None => arena
+                .history
+                .iter()
+                .rev()
+                .find(|r| r.generation == generation)
+                .map(|r| {
+                    r.predator_scores
+                        .iter()
+                        .chain(r.prey_scores.iter())
+                        .copied()
+                        .collect()
+                })
+                .unwrap_or_default(),
+        };
+        for (o, s) in recording.organisms.iter_mut().zip(scores) {
+            o.score = Some(s);
+        }
+        if let Some(best) = rc.record_best {
+            recording
+                .organisms
+                .sort_by(|a, b| b.score.unwrap_or(0.0).total_cmp(&a.score.unwrap_or(0.0)));
+            recording.organisms.truncate(best);
+        }
+
+        if self.keep {
+            self.finished = Some(recording);
+            return;
+        }
+        match recording.save(sc) {
+            Ok(path) => println!("Saved recording {:?}", path),
+            Err(err) => println!("Error saving recording, {:?}", err),
+        }
+    }
+}
+
+// Record every organism of the first trial of every nth generation, the best are saved
+// once the generation has been scored
+pub fn record_trajectories(
+    rc: Res<ReplayConfig>,
+    gc: Res<GenerationConfig>,
+    sc: Res<SaveConfig>,
+    ol: Res<OrganismList>,
+    env: Res<Environment>,
+    trials: Res<Trials>,
+    stats: Res<GenerationStats>,
+    arena: Res<Arena>,
+    mut recorder: ResMut<Recorder>,
+    joints: Query<&Transform, With<Joint>>,
+    bones: Query<&Transform, (With<Bone>, Without<Joint>)>,
+    muscles: Query<&Muscle>,
+) {
+    let generation = gc.cur_generation;
+    if let Some(r) = &recorder.recording {
+        if r.generation != generation {
+            recorder.finish(&rc, &sc, &stats, &arena);
+        }
+    }
+    if rc.record_every == 0 || generation % rc.record_every != 0 {
+        return;
+    }
+    if !ol.is_spawned || trials.cur_trial != 0 {
+        return;
+    }
+
+    // Organisms spawned this frame don't exist until their commands are applied, the frame is
+    // skipped instead of recording them at the origin
+    let mut frame_joints = Vec::with_capacity(ol.builders.len());
+    for o in ol.organisms.iter().take(ol.builders.len()) {
+        let positions = o
+            .joints
+            .iter()
+            .map(|j| joints.get(*j).map(|t| t.translation.truncate()))
+            .collect::<Result<Vec<Vec2>, _>>();
+        match positions {
+            Ok(p) => frame_joints.push(p),
+            Err(_) => return,
+        }
+    }
+
+    let recording = recorder.recording.get_or_insert_with(|| Recording {
+        generation,
+        times: vec![],
+        organisms: ol
+            .builders
+            .iter()
+            .enumerate()
+            .map(|(i, b)| OrganismRecording {
+                id: b.lineage.id,
+                lane_offset: env.get_lane_offset(0, i),
+                bones: b.get_bones().clone(),
+                ..Default::default()
+            })
+            .collect(),
+        environment: env
+            .get_description(0)
+            .map(|d| d.shapes.clone())
+            .unwrap_or_default(),
+    });
+    recording.times.push(gc.timer.elapsed_secs());
+
+    // Only the first group when trials run in parallel
+    for ((o, r), j) in ol
+        .organisms
+        .iter()
+        .zip(recording.organisms.iter_mut())
+        .zip(frame_joints)
+    {
+        r.joints.push(j);
+
+        let mut ends = Vec::with_capacity(o.muscles.len());
+        let mut activations = Vec::with_capacity(o.muscles.len());
+        for m in o.muscles.iter().filter_map(|m| muscles.get(*m).ok()) {
+            let end = |i: usize| match bones.get(m.bones[i]) {
+                Ok(t) => {
+                    t.translation.truncate() + (t.rotation * m.anchors[i].extend(0.0)).truncate()
+                }
+                Err(_) => Vec2::ZERO,
+            };
+            ends.push([end(0), end(1)]);
+            activations.push(m.len_modifier);
+        }
+        r.muscles.push(ends);
+        r.activations.push(activations);
+    }
+}
@@ -1,11 +1,23 @@
-use bevy::prelude::{App, Commands, IntoSystemConfigs, Plugin, Res, ResMut, Resource, Update};
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::{
+        App, Assets, Commands, IntoSystemConfigs, Mesh, Plugin, Res, ResMut, Resource, Update,
+    },
+    sprite::ColorMaterial,
+};
 
 use crate::{
-    config::structs::{GenerationConfig, SaveConfig},
-    generation::environment::Environment,
-    handles::Handles,
-    organism::organism_list::OrganismList,
-    organism_constructor::constructor::Constructor,
+    arena::{Arena, ArenaConfig, NUM_ARENA_STIMULI},
+    config::structs::{EnvironmentConfig, EnvironmentLayout, GenerationConfig},
+    generation::{curriculum::Curriculum, environment::Environment, trial::Trials},
+    handles::{set_organism_alpha, Handles},
+    organism::{library::OrganismLibrary, organism_list::OrganismList},
+    organism_constructor::{
+        constructor::Constructor, joint_settings::BoneJointSettings, motor_settings::MotorSettings,
+    },
+    replay::{ReplayConfig, ReplayPlayer},
+    start_menu::StartMenu,
+    task::{TaskConfig, TaskState},
 };
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -14,53 +26,142 @@ pub enum Scene {
     StartMenu,
     OrganismConstructor,
     OrganismSimulation,
+    OrganismArena,
+    Replay,
 }
 impl Scene {
-    fn pre_change(
-        &self,
-        commands: &mut Commands,
-        con: &mut Constructor,
-        ol: &mut OrganismList,
-        env: &Environment,
-    ) {
+    fn pre_change(&self, r: &mut SceneResources) {
         match self {
             Scene::NoScene => {}
-            Scene::StartMenu => {}
+            Scene::StartMenu => {
+                r.sm.despawn(&mut r.commands);
+            }
             Scene::OrganismConstructor => {
-                con.despawn(commands);
+                r.con.despawn(&mut r.commands);
             }
             Scene::OrganismSimulation => {
-                ol.despawn(commands);
-                env.despawn(commands);
+                r.ol.despawn(&mut r.commands);
+                r.env.despawn(&mut r.commands);
+                r.sim.ts.despawn(&mut r.commands);
+                set_organism_alpha(&r.handles, &mut r.materials, 1.0);
+            }
+            Scene::OrganismArena => {
+                r.ol.despawn(&mut r.commands);
+                r.env.despawn(&mut r.commands);
+                r.sim.ts.despawn(&mut r.commands);
+                r.ol.team_split = None;
+                r.sim.arena.active = false;
+            }
+            Scene::Replay => {
+                r.sim.player.unload();
             }
         }
     }
-    fn post_change(
-        &self,
-        commands: &mut Commands,
-        con: &mut Constructor,
-        ol: &mut OrganismList,
-        env: &mut Environment,
-        gc: &mut GenerationConfig,
-        handles: &Handles,
-        sc: &SaveConfig,
-    ) {
+    fn post_change(&self, r: &mut SceneResources) {
         match self {
             Scene::NoScene => {}
-            Scene::StartMenu => {}
+            Scene::StartMenu => {
+                r.sm.spawn(&mut r.commands, &r.sim.tc, &r.sim.ac, &r.library);
+            }
             Scene::OrganismConstructor => {
-                con.spawn(commands);
+                r.con.spawn(&mut r.commands, &r.bjs, &r.ms);
+                // Open the design picked from the library
+                let editing = r.library.editing.take();
+                if let Some(bp) = editing.and_then(|i| r.library.blueprints.get(i)) {
+                    r.con.load_blueprint(&mut r.commands, &r.handles, bp);
+                }
             }
             Scene::OrganismSimulation => {
-                // setup_builders(ol, gc, sc);
-                gc.timer.reset();
-                ol.spawn(commands, handles, gc.vertical_sep);
-                env.spawn(commands, &handles.block_mesh, &handles.block_material, gc);
+                r.gc.timer.reset();
+                set_organism_alpha(&r.handles, &mut r.materials, r.ec.get_organism_alpha());
+                r.sim.trials.reset(r.ol.builders.len());
+                r.env.trials = r.gc.trials.get_concurrent_trials(0);
+                r.env.task_description = r.sim.tc.get_environment(&r.ec);
+
+                // The environment is spawned first so organisms can use its spawn points
+                r.env.spawn(
+                    &mut r.commands,
+                    &mut r.meshes,
+                    &r.handles,
+                    &r.gc,
+                    &r.ec,
+                    &r.sim.curriculum,
+                );
+                r.ol.spawn(
+                    &mut r.commands,
+                    &r.handles,
+                    &r.gc,
+                    &r.env,
+                    r.sim.tc.task.get_num_stimuli(),
+                );
+            }
+            Scene::OrganismArena => {
+                r.gc.timer.reset();
+                let num_predators = r.sim.ac.num_predators;
+                r.sim.arena.reset(num_predators, r.ol.builders.len());
+                r.ol.team_split = Some(num_predators);
+                r.env.trials = vec![0];
+                r.env.task_description = Some(r.sim.ac.get_environment());
+
+                // Both populations share the arena floor
+                let mut ec = r.ec.clone();
+                ec.layout = EnvironmentLayout::Shared;
+                ec.file = None;
+                r.env.spawn(
+                    &mut r.commands,
+                    &mut r.meshes,
+                    &r.handles,
+                    &r.gc,
+                    &ec,
+                    &r.sim.curriculum,
+                );
+                r.ol.spawn(
+                    &mut r.commands,
+                    &r.handles,
+                    &r.gc,
+                    &r.env,
+                    NUM_ARENA_STIMULI,
+                );
+            }
+            Scene::Replay => {
+                r.sim.player.load(&r.sim.rc);
             }
         }
     }
 }
 
+// Resources used to set up what organisms are evaluated on
+#[derive(SystemParam)]
+pub struct SimulationResources<'w> {
+    curriculum: Res<'w, Curriculum>,
+    trials: ResMut<'w, Trials>,
+    tc: Res<'w, TaskConfig>,
+    ts: ResMut<'w, TaskState>,
+    ac: Res<'w, ArenaConfig>,
+    arena: ResMut<'w, Arena>,
+    rc: Res<'w, ReplayConfig>,
+    player: ResMut<'w, ReplayPlayer>,
+}
+
+// Resources scenes need when being spawned and despawned
+#[derive(SystemParam)]
+pub struct SceneResources<'w, 's> {
+    commands: Commands<'w, 's>,
+    sm: ResMut<'w, StartMenu>,
+    con: ResMut<'w, Constructor>,
+    ol: ResMut<'w, OrganismList>,
+    env: ResMut<'w, Environment>,
+    gc: ResMut<'w, GenerationConfig>,
+    ec: Res<'w, EnvironmentConfig>,
+    sim: SimulationResources<'w>,
+    handles: Res<'w, Handles>,
+    bjs: Res<'w, BoneJointSettings>,
+    ms: Res<'w, MotorSettings>,
+    library: ResMut<'w, OrganismLibrary>,
+    materials: ResMut<'w, Assets<ColorMaterial>>,
+    meshes: ResMut<'w, Assets<Mesh>>,
+}
+
 #[derive(Resource)]
 pub struct CurrentScene {
     cur_scene: Scene,
@@ -69,32 +170,23 @@ pub struct CurrentScene {
 pub fn is_simulation(cs: Res<CurrentScene>) -> bool {
     return cs.cur_scene == Scene::OrganismSimulation;
 }
+pub fn is_arena(cs: Res<CurrentScene>) -> bool {
+    return cs.cur_scene == Scene::OrganismArena;
+}
+pub fn is_replay(cs: Res<CurrentScene>) -> bool {
+    return cs.cur_scene == Scene::Replay;
+}
+pub fn is_start_menu(cs: Res<CurrentScene>) -> bool {
+    return cs.cur_scene == Scene::StartMenu;
+}
 
 fn scene_needs_change(cs: Res<CurrentScene>) -> bool {
     return cs.cur_scene != cs.next_scene;
 }
-fn change_scene(
-    mut commands: Commands,
-    mut cs: ResMut<CurrentScene>,
-    mut con: ResMut<Constructor>,
-    mut ol: ResMut<OrganismList>,
-    mut env: ResMut<Environment>,
-    mut gc: ResMut<GenerationConfig>,
-    sc: Res<SaveConfig>,
-    handles: Res<Handles>,
-) {
-    cs.cur_scene
-        .pre_change(&mut commands, &mut con, &mut ol, &env);
+fn change_scene(mut cs: ResMut<CurrentScene>, mut r: SceneResources) {
+    cs.cur_scene.pre_change(&mut r);
     cs.cur_scene = cs.next_scene;
-    cs.next_scene.post_change(
-        &mut commands,
-        &mut con,
-        &mut ol,
-        &mut env,
-        &mut gc,
-        &handles,
-        &sc,
-    );
+    cs.next_scene.post_change(&mut r);
 }
 
 pub struct SceneManagerPlugin;
@@ -102,7 +194,7 @@ impl Plugin for SceneManagerPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(CurrentScene {
             cur_scene: Scene::NoScene,
-            next_scene: Scene::OrganismConstructor,
+            next_scene: Scene::StartMenu,
         })
         .add_systems(Update, (change_scene).run_if(scene_needs_change));
     }
@@ -0,0 +1,301 @@
+use bevy::{
+    prelude::{
+        default, App, BuildChildren, Button, ButtonBundle, Changed, ChildBuilder, Commands,
+        Component, DespawnRecursiveExt, Entity, IntoSystemConfigs, NodeBundle, Plugin, Query, Res,
+        ResMut, Resource, TextBundle, Update, With,
+    },
+    text::TextStyle,
+    ui::{
+        AlignItems, BackgroundColor, BorderColor, Display, FlexDirection, GridTrack, Interaction,
+        JustifyContent, Style, UiRect, Val,
+    },
+};
+
+use crate::{
+    arena::ArenaConfig,
+    color_palette,
+    config::structs::GenerationConfig,
+    generation::start_run,
+    organism::{
+        library::{OrganismLibrary, LIBRARY_FOLDER},
+        organism_list::OrganismList,
+    },
+    scene_manager::{is_start_menu, CurrentScene, Scene},
+    task::{Task, TaskConfig},
+};
+
+pub struct StartMenuPlugin;
+impl Plugin for StartMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(StartMenu::default())
+            .insert_resource(OrganismLibrary::load(LIBRARY_FOLDER))
+            .add_systems(
+                Update,
+                (
+                    handle_task_buttons,
+                    handle_arena_button,
+                    handle_library_buttons,
+                    handle_start_button,
+                    handle_edit_button,
+                )
+                    .run_if(is_start_menu),
+            );
+    }
+}
+
+// Menu shown before constructing an organism, used to pick the task or the arena and an
+// organism from the library
+#[derive(Resource, Default)]
+pub struct StartMenu {
+    menu: Option<Entity>,
+}
+impl StartMenu {
+    pub fn spawn(
+        &mut self,
+        commands: &mut Commands,
+        tc: &TaskConfig,
+        ac: &ArenaConfig,
+        library: &OrganismLibrary,
+    ) {
+        let num_rows = Task::ALL.len() + library.blueprints.len() + 5;
+        let menu = commands
+            .spawn(NodeBundle {
+                style: Style {
+                    display: Display::Grid,
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    grid_template_rows: vec![GridTrack::auto(); num_rows],
+                    grid_template_columns: vec![GridTrack::percent(100.0)],
+                    ..default()
+                },
+                background_color: BackgroundColor(color_palette::SECONDARY),
+                ..default()
+            })
+            .with_children(|grid| {
+                grid.spawn(TextBundle::from_section(
+                    "Select a task",
+                    TextStyle {
+                        font_size: 60.0,
+                        color: color_palette::PRIMARY,
+                        ..default()
+                    },
+                ));
+                for task in Task::ALL {
+                    spawn_button(grid, TaskButton(task), task.get_name(), tc.task == task);
+                }
+                spawn_button(grid, ArenaButton, "Predator/Prey Arena", ac.enable);
+                grid.spawn(TextBundle::from_section(
+                    "Organism library",
+                    TextStyle {
+                        font_size: 40.0,
+                        color: color_palette::PRIMARY,
+                        ..default()
+                    },
+                ));
+                for (i, bp) in library.blueprints.iter().enumerate() {
+                    let selected = library.selected == Some(i);
+                    spawn_library_button(grid, i, &bp.name, &bp.description, selected);
+                }
+                spawn_button(grid, StartButton, "Start", false);
+                spawn_button(grid, EditButton, "Edit", false);
+            })
+            .id();
+        self.menu = Some(menu);
+    }
+
+    pub fn despawn(&mut self, commands: &mut Commands) {
+        if let Some(e) = self.menu {
+            commands.entity(e).despawn_recursive();
+            self.menu = None;
+        }
+    }
+}
+
+fn spawn_button(grid: &mut ChildBuilder, button: impl Component, text: &str, selected: bool) {
+    let color = match selected {
+        true => color_palette::SELECTED,
+        false => color_palette::PRIMARY,
+    };
+    grid.spawn((
+        button,
+        ButtonBundle {
+            style: Style {
+                margin: UiRect::all(Val::Percent(1.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            border_color: BorderColor(color_palette::TERTIARY),
+            background_color: BackgroundColor(color),
+            ..default()
+        },
+    ))
+    .with_children(|button| {
+        button.spawn(TextBundle::from_section(
+            text,
+            TextStyle {
+                font_size: 40.0,
+                color: color_palette::TERTIARY,
+                ..default()
+            },
+        ));
+    });
+}
+
+// Like other buttons with the description under the name
+fn spawn_library_button(
+    grid: &mut ChildBuilder,
+    index: usize,
+    name: &str,
+    description: &str,
+    selected: bool,
+) {
+    let color = match selected {
+        true => color_palette::SELECTED,
+        false => color_palette::PRIMARY,
+    };
+    grid.spawn((
+        LibraryButton(index),
+        ButtonBundle {
+            style: Style {
+                margin: UiRect::all(Val::Percent(1.0)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            border_color: BorderColor(color_palette::TERTIARY),
+            background_color: BackgroundColor(color),
+            ..default()
+        },
+    ))
+    .with_children(|button| {
+        button.spawn(TextBundle::from_section(
+            name,
+            TextStyle {
+                font_size: 32.0,
+                color: color_palette::TERTIARY,
+                ..default()
+            },
+        ));
+        button.spawn(TextBundle::from_section(
+            description,
+            TextStyle {
+                font_size: 18.0,
+                color: color_palette::SECONDARY,
+                ..default()
+            },
+        ));
+    });
+}
+
+#[derive(Component)]
+pub struct TaskButton(Task);
+
+// Picks the library blueprint the run starts from or the constructor opens
+#[derive(Component)]
+pub struct LibraryButton(usize);
+
+// Toggles the arena, which replaces the task when enabled
+#[derive(Component)]
+pub struct ArenaButton;
+
+// Starts a run from the picked blueprint, or opens the constructor when none is picked
+#[derive(Component)]
+pub struct StartButton;
+
+// Opens the picked blueprint in the constructor
+#[derive(Component)]
+pub struct EditButton;
+
+fn handle_task_buttons(
+    pressed: Query<(&Interaction, &TaskButton), (Changed<Interaction>, With<Button>)>,
+    mut buttons: Query<(&mut BackgroundColor, &TaskButton), With<Button>>,
+    mut tc: ResMut<TaskConfig>,
+) {
+    for (i, tb) in pressed.iter() {
+        if *i == Interaction::Pressed {
+            tc.task = tb.0;
+        }
+    }
+
+    for (mut color, tb) in buttons.iter_mut() {
+        color.0 = match tc.task == tb.0 {
+            true => color_palette::SELECTED,
+            false => color_palette::PRIMARY,
+        };
+    }
+}
+
+fn handle_arena_button(
+    mut buttons: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<ArenaButton>),
+    >,
+    mut ac: ResMut<ArenaConfig>,
+) {
+    for (i, mut color) in buttons.iter_mut() {
+        if *i == Interaction::Pressed {
+            ac.enable = !ac.enable;
+            color.0 = match ac.enable {
+                true => color_palette::SELECTED,
+                false => color_palette::PRIMARY,
+            };
+        }
+    }
+}
+
+fn handle_library_buttons(
+    pressed: Query<(&Interaction, &LibraryButton), (Changed<Interaction>, With<Button>)>,
+    mut buttons: Query<(&mut BackgroundColor, &LibraryButton), With<Button>>,
+    mut library: ResMut<OrganismLibrary>,
+) {
+    for (i, lb) in pressed.iter() {
+        if *i == Interaction::Pressed {
+            // Pressing the picked blueprint again unpicks it
+            library.selected = match library.selected == Some(lb.0) {
+                true => None,
+                false => Some(lb.0),
+            };
+        }
+    }
+
+    for (mut color, lb) in buttons.iter_mut() {
+        color.0 = match library.selected == Some(lb.0) {
+            true => color_palette::SELECTED,
+            false => color_palette::PRIMARY,
+        };
+    }
+}
+
+fn handle_start_button(
+    pressed: Query<&Interaction, (Changed<Interaction>, With<StartButton>)>,
+    library: Res<OrganismLibrary>,
+    gc: Res<GenerationConfig>,
+    ac: Res<ArenaConfig>,
+    mut ol: ResMut<OrganismList>,
+    mut cs: ResMut<CurrentScene>,
+) {
+    for i in pressed.iter() {
+        if *i != Interaction::Pressed {
+            continue;
+        }
+        match library.get_selected() {
+            Some(bp) => start_run(bp.get_builder(), &gc, &ac, &mut ol, &mut cs),
+            None => cs.next_scene = Scene::OrganismConstructor,
+        }
+    }
+}
+
+fn handle_edit_button(
+    pressed: Query<&Interaction, (Changed<Interaction>, With<EditButton>)>,
+    mut library: ResMut<OrganismLibrary>,
+    mut cs: ResMut<CurrentScene>,
+) {
+    for i in pressed.iter() {
+        if *i == Interaction::Pressed {
+            library.editing = library.selected;
+            cs.next_scene = Scene::OrganismConstructor;
+        }
+    }
+}
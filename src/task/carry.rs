@@ -0,0 +1,150 @@
+use bevy::{
+    math::vec2,
+    prelude::{default, Commands, Query, Transform, Vec2, Vec3, With, Without},
+    sprite::MaterialMesh2dBundle,
+};
+use bevy_rapier2d::prelude::{Collider, ColliderMassProperties, Friction, RigidBody};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    collider_layer::ObjectCollisionBundle,
+    handles::Handles,
+    organism::{joint::Joint, organism_list::OrganismList},
+};
+
+use super::{get_centre, TaskConfig, TaskEntity, TaskState};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct CarryConfig {
+    // Horizontal distance from the spawn point to the loose object
+    pub object_offset: f32,
+    pub object_radius: f32,
+    pub object_density: f32,
+    // Horizontal distance from the spawn point to the goal
+    pub goal_dist: f32,
+    // How close the object needs to get to the goal
+    pub reach_radius: f32,
+    // Score for getting the object to the goal, added to the progress made
+    pub reward: f32,
+}
+impl Default for CarryConfig {
+    fn default() -> Self {
+        Self {
+            object_offset: 80.0,
+            object_radius: 10.0,
+            object_density: 0.05,
+            goal_dist: 800.0,
+            reach_radius: 40.0,
+            reward: 1000.0,
+        }
+    }
+}
+
+pub struct CarryProgress {
+    object: usize,
+    goal: Vec2,
+    start_dist: f32,
+    closest_dist: f32,
+}
+
+// Spawn a loose object and a goal for each organism, only its own organism can move the object
+pub fn reset_carries(
+    commands: &mut Commands,
+    tc: &TaskConfig,
+    ol: &OrganismList,
+    handles: &Handles,
+    ts: &mut TaskState,
+) {
+    let cc = &tc.carry;
+    for (i, o) in ol.organisms.iter().enumerate() {
+        let object_pos = o.start_pos + vec2(cc.object_offset, 0.0);
+        let goal = o.start_pos + vec2(cc.goal_dist, 0.0);
+
+        let object = commands
+            .spawn((
+                MaterialMesh2dBundle {
+                    mesh: handles.joint_mesh.clone(),
+                    material: handles.block_material.clone(),
+                    transform: Transform {
+                        translation: object_pos.extend(-0.1),
+                        scale: Vec3::ONE * cc.object_radius,
+                        ..default()
+                    },
+                    ..default()
+                },
+                RigidBody::Dynamic,
+                Collider::ball(1.0),
+                ColliderMassProperties::Density(cc.object_density),
+                Friction::coefficient(0.7),
+                ObjectCollisionBundle::new(i as u32),
+                TaskEntity,
+            ))
+            .id();
+        let marker = commands
+            .spawn((
+                MaterialMesh2dBundle {
+                    mesh: handles.joint_mesh.clone(),
+                    material: handles.target_material.clone(),
+                    transform: Transform {
+                        translation: goal.extend(-0.4),
+                        scale: Vec3::ONE * cc.reach_radius,
+                        ..default()
+                    },
+                    ..default()
+                },
+                TaskEntity,
+            ))
+            .id();
+
+        ts.carries.push(CarryProgress {
+            object: ts.ents.len(),
+            goal,
+            start_dist: object_pos.distance(goal),
+            closest_dist: object_pos.distance(goal),
+        });
+        ts.ents.push(object);
+        ts.ents.push(marker);
+    }
+}
+
+// Score how close each organism got its object to the goal
+pub fn update_carries(
+    tc: &TaskConfig,
+    ol: &OrganismList,
+    ts: &mut TaskState,
+    joints: &Query<&Transform, With<Joint>>,
+    task_ents: &Query<&mut Transform, (With<TaskEntity>, Without<Joint>)>,
+) {
+    let cc = &tc.carry;
+    for (i, o) in ol.organisms.iter().enumerate() {
+        let carry = match ts.carries.get_mut(i) {
+            Some(c) => c,
+            None => continue,
+        };
+        let object_pos = match task_ents.get(ts.ents[carry.object]) {
+            Ok(t) => t.translation.truncate(),
+            Err(_) => continue,
+        };
+        let centre = get_centre(o, joints);
+        let to_object = object_pos - centre;
+        let to_goal = carry.goal - object_pos;
+
+        let dist = to_goal.length();
+        carry.closest_dist = carry.closest_dist.min(dist);
+        let reward = match carry.closest_dist <= cc.reach_radius {
+            true => cc.reward,
+            false => 0.0,
+        };
+
+        ts.scores[i] = reward + (carry.start_dist - carry.closest_dist).max(0.0);
+        ts.stimuli[i] = vec![
+            to_object.normalize_or_zero().x,
+            to_object.normalize_or_zero().y,
+            (to_object.length() / 1000.0).min(1.0),
+            to_goal.normalize_or_zero().x,
+            to_goal.normalize_or_zero().y,
+            (dist / 1000.0).min(1.0),
+        ];
+    }
+}
@@ -0,0 +1,73 @@
+use bevy::{
+    math::vec2,
+    prelude::{Query, Transform, With},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::structs::EnvironmentConfig,
+    generation::env_description::{EnvironmentDescription, Shape, ShapeDescription},
+    organism::{joint::Joint, organism_list::OrganismList},
+};
+
+use super::{get_centre, TaskConfig, TaskState};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ClimbConfig {
+    // Distance from the spawn point to the first step
+    pub start_x: f32,
+    pub num_steps: usize,
+    pub step_width: f32,
+    pub step_height: f32,
+    pub friction: f32,
+}
+impl Default for ClimbConfig {
+    fn default() -> Self {
+        Self {
+            start_x: 200.0,
+            num_steps: 8,
+            step_width: 60.0,
+            step_height: 15.0,
+            friction: 0.9,
+        }
+    }
+}
+impl ClimbConfig {
+    // A flat floor followed by a staircase, a single tall step makes a wall
+    pub fn get_environment(&self, ec: &EnvironmentConfig) -> EnvironmentDescription {
+        let floor_height = ec.floor_height;
+        let mut description = ec.get_floor();
+
+        for i in 0..self.num_steps {
+            let height = (i + 1) as f32 * self.step_height;
+            let x = self.start_x + (i as f32 + 0.5) * self.step_width;
+            description.shapes.push(ShapeDescription::new(
+                Shape::Box {
+                    extents: vec2(self.step_width, height),
+                },
+                vec2(x, (floor_height + height) * 0.5),
+                self.friction,
+            ));
+        }
+        return description;
+    }
+}
+
+// Score the highest each organism's centre got above its spawn point
+pub fn update_climbs(
+    tc: &TaskConfig,
+    ol: &OrganismList,
+    ts: &mut TaskState,
+    joints: &Query<&Transform, With<Joint>>,
+) {
+    for (i, o) in ol.organisms.iter().enumerate() {
+        let centre = get_centre(o, joints);
+        let height = centre.y - o.start_pos.y;
+        ts.scores[i] = ts.scores[i].max(height);
+        ts.stimuli[i] = vec![
+            (height / 100.0).clamp(-1.0, 1.0),
+            ((o.start_pos.x + tc.climb.start_x - centre.x) / 1000.0).clamp(-1.0, 1.0),
+        ];
+    }
+}
@@ -0,0 +1,181 @@
+use bevy::{
+    math::vec2,
+    prelude::{default, Commands, Query, Transform, Vec2, Vec3, With, Without},
+    sprite::MaterialMesh2dBundle,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::structs::GenerationConfig,
+    generation::trial::Trials,
+    handles::Handles,
+    organism::{joint::Joint, organism_list::OrganismList},
+};
+
+use super::{get_centre, TaskConfig, TaskEntity, TaskState};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct GoalConfig {
+    // Horizontal distance range of a target from the previous target or spawn point
+    pub min_dist: f32,
+    pub max_dist: f32,
+    // Max height of a target above the previous target or spawn point
+    pub max_height: f32,
+    // Allow targets to the left as well as the right
+    pub both_directions: bool,
+    // How close the centre of an organism needs to get to collect a target
+    pub reach_radius: f32,
+    // Place a new target after one is collected
+    pub successive: bool,
+    pub max_targets: usize,
+    // Score for each collected target, added to the progress made towards the current one
+    pub collect_reward: f32,
+}
+impl Default for GoalConfig {
+    fn default() -> Self {
+        Self {
+            min_dist: 200.0,
+            max_dist: 600.0,
+            max_height: 40.0,
+            both_directions: true,
+            reach_radius: 30.0,
+            successive: true,
+            max_targets: 8,
+            collect_reward: 1000.0,
+        }
+    }
+}
+
+// Targets an organism needs to reach and how far it got
+pub struct GoalProgress {
+    targets: Vec<Vec2>,
+    cur_target: usize,
+    collected: usize,
+    start_dist: f32,
+    closest_dist: f32,
+}
+
+// Generate the same target offsets for every organism in a trial
+fn gen_offsets(gc: &GoalConfig, seed: u64) -> Vec<Vec2> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let num_targets = match gc.successive {
+        true => gc.max_targets.max(1),
+        false => 1,
+    };
+
+    return (0..num_targets)
+        .map(|_| {
+            let mut x = rng.gen_range(gc.min_dist..=gc.max_dist.max(gc.min_dist));
+            if gc.both_directions && rng.gen::<bool>() {
+                x = -x;
+            }
+            vec2(x, rng.gen_range(0.0..=gc.max_height.max(0.0)))
+        })
+        .collect();
+}
+
+// Place targets relative to each organism's spawn point
+pub fn reset_goals(
+    commands: &mut Commands,
+    tc: &TaskConfig,
+    gc: &GenerationConfig,
+    trials: &Trials,
+    ol: &OrganismList,
+    handles: &Handles,
+    ts: &mut TaskState,
+) {
+    let mut offsets = vec![];
+    for o in ol.organisms.iter() {
+        // Organisms in a group share a trial
+        while offsets.len() <= o.group {
+            let trial = trials.cur_trial + offsets.len();
+            offsets.push(gen_offsets(&tc.goal, tc.get_seed(gc.cur_generation, trial)));
+        }
+
+        let mut pos = o.start_pos;
+        let targets = offsets[o.group]
+            .iter()
+            .map(|offset| {
+                pos += *offset;
+                pos
+            })
+            .collect::<Vec<Vec2>>();
+        let start_dist = o.start_pos.distance(targets[0]);
+
+        let ent = commands
+            .spawn((
+                MaterialMesh2dBundle {
+                    mesh: handles.joint_mesh.clone(),
+                    material: handles.target_material.clone(),
+                    transform: Transform {
+                        translation: targets[0].extend(-0.4),
+                        scale: Vec3::ONE * tc.goal.reach_radius,
+                        ..default()
+                    },
+                    ..default()
+                },
+                TaskEntity,
+            ))
+            .id();
+        ts.ents.push(ent);
+
+        ts.goals.push(GoalProgress {
+            targets,
+            cur_target: 0,
+            collected: 0,
+            start_dist,
+            closest_dist: start_dist,
+        });
+    }
+}
+
+// Collect reached targets and give each organism the direction and distance to its target
+pub fn update_goals(
+    tc: &TaskConfig,
+    ol: &OrganismList,
+    ts: &mut TaskState,
+    joints: &Query<&Transform, With<Joint>>,
+    task_ents: &mut Query<&mut Transform, (With<TaskEntity>, Without<Joint>)>,
+) {
+    for (i, o) in ol.organisms.iter().enumerate() {
+        let goal = match ts.goals.get_mut(i) {
+            Some(g) => g,
+            None => continue,
+        };
+        let centre = get_centre(o, joints);
+        let mut to_target = goal.targets[goal.cur_target] - centre;
+
+        if goal.collected <= goal.cur_target && to_target.length() <= tc.goal.reach_radius {
+            goal.collected += 1;
+
+            // Move on to the next target
+            if goal.cur_target + 1 < goal.targets.len() {
+                goal.cur_target += 1;
+                let target = goal.targets[goal.cur_target];
+                goal.start_dist = centre.distance(target);
+                goal.closest_dist = goal.start_dist;
+                to_target = target - centre;
+                if let Ok(mut t) = task_ents.get_mut(ts.ents[i]) {
+                    t.translation = target.extend(-0.4);
+                }
+            }
+        }
+
+        let dist = to_target.length();
+        if goal.collected <= goal.cur_target {
+            goal.closest_dist = goal.closest_dist.min(dist);
+        } else {
+            goal.closest_dist = 0.0;
+        }
+
+        ts.scores[i] = goal.collected as f32 * tc.goal.collect_reward
+            + (goal.start_dist - goal.closest_dist).max(0.0);
+        ts.stimuli[i] = vec![
+            to_target.normalize_or_zero().x,
+            to_target.normalize_or_zero().y,
+            (dist / 1000.0).min(1.0),
+        ];
+    }
+}
@@ -0,0 +1,72 @@
+use bevy::prelude::{Query, Transform, With};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::structs::GenerationConfig,
+    organism::{joint::Joint, organism_list::OrganismList},
+};
+
+use super::{TaskConfig, TaskState};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct JumpConfig {
+    // Seconds to ignore while organisms drop onto the floor
+    pub settle_time: f32,
+}
+impl Default for JumpConfig {
+    fn default() -> Self {
+        Self { settle_time: 2.0 }
+    }
+}
+
+// Lowest and highest positions of an organism's lowest joint
+pub struct JumpProgress {
+    ground: f32,
+    highest: f32,
+}
+
+pub fn reset_jumps(ol: &OrganismList, ts: &mut TaskState) {
+    for _ in ol.organisms.iter() {
+        ts.jumps.push(JumpProgress {
+            ground: f32::INFINITY,
+            highest: f32::NEG_INFINITY,
+        });
+    }
+}
+
+// Score the highest the lowest joint got above the lowest point it touched
+pub fn update_jumps(
+    tc: &TaskConfig,
+    gc: &GenerationConfig,
+    ol: &OrganismList,
+    ts: &mut TaskState,
+    joints: &Query<&Transform, With<Joint>>,
+) {
+    if gc.timer.elapsed_secs() < tc.jump.settle_time {
+        return;
+    }
+
+    for (i, o) in ol.organisms.iter().enumerate() {
+        let jump = match ts.jumps.get_mut(i) {
+            Some(j) => j,
+            None => continue,
+        };
+        let lowest = o
+            .joints
+            .iter()
+            .filter_map(|j| joints.get(*j).ok())
+            .map(|t| t.translation.y)
+            .fold(f32::INFINITY, f32::min);
+        if !lowest.is_finite() {
+            continue;
+        }
+
+        jump.ground = jump.ground.min(lowest);
+        jump.highest = jump.highest.max(lowest);
+
+        let height = jump.highest - jump.ground;
+        ts.scores[i] = height;
+        ts.stimuli[i] = vec![((lowest - jump.ground) / 100.0).min(1.0)];
+    }
+}
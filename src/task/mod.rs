@@ -0,0 +1,220 @@
+use bevy::prelude::{
+    App, Commands, Component, Entity, IntoSystemConfigs, Plugin, Query, Res, ResMut, Resource,
+    Transform, Update, Vec2, With, Without,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::structs::{EnvironmentConfig, GenerationConfig},
+    generation::{env_description::EnvironmentDescription, trial::Trials},
+    handles::Handles,
+    organism::{
+        joint::Joint,
+        organism::Organism,
+        organism_list::{update_brains, OrganismList},
+    },
+    scene_manager::is_simulation,
+};
+
+use self::{
+    carry::{reset_carries, update_carries, CarryConfig, CarryProgress},
+    climb::{update_climbs, ClimbConfig},
+    goal::{reset_goals, update_goals, GoalConfig, GoalProgress},
+    jump::{reset_jumps, update_jumps, JumpConfig, JumpProgress},
+};
+
+pub mod carry;
+pub mod climb;
+pub mod goal;
+pub mod jump;
+
+// What organisms are evolved to do
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Task {
+    // Move as far right as possible
+    RunRight,
+    // Reach targets placed around the spawn point
+    GoalSeek,
+    // Get the lowest joint as high off the floor as possible
+    Jump,
+    // Climb a staircase or wall
+    Climb,
+    // Push or carry a loose object to a goal
+    Carry,
+}
+impl Task {
+    pub const ALL: [Task; 5] = [
+        Task::RunRight,
+        Task::GoalSeek,
+        Task::Jump,
+        Task::Climb,
+        Task::Carry,
+    ];
+
+    pub fn get_name(&self) -> &'static str {
+        return match self {
+            Task::RunRight => "Run Right",
+            Task::GoalSeek => "Goal Seek",
+            Task::Jump => "Jump",
+            Task::Climb => "Climb",
+            Task::Carry => "Carry",
+        };
+    }
+
+    // Number of extra brain inputs the task supplies
+    pub fn get_num_stimuli(&self) -> usize {
+        return match self {
+            Task::RunRight => 0,
+            // Direction and distance to the target
+            Task::GoalSeek => 3,
+            // Height above the floor
+            Task::Jump => 1,
+            // Height climbed and distance to the first step
+            Task::Climb => 2,
+            // Direction and distance to the object and from the object to the goal
+            Task::Carry => 6,
+        };
+    }
+}
+
+#[derive(Resource, Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct TaskConfig {
+    pub task: Task,
+    // Seed for target placement, combined with the generation and trial
+    pub seed: u64,
+    pub goal: GoalConfig,
+    pub jump: JumpConfig,
+    pub climb: ClimbConfig,
+    pub carry: CarryConfig,
+}
+impl Default for TaskConfig {
+    fn default() -> Self {
+        Self {
+            task: Task::RunRight,
+            seed: 0,
+            goal: Default::default(),
+            jump: Default::default(),
+            climb: Default::default(),
+            carry: Default::default(),
+        }
+    }
+}
+impl TaskConfig {
+    // Environment the task needs, None uses the environment config
+    pub fn get_environment(&self, ec: &EnvironmentConfig) -> Option<EnvironmentDescription> {
+        return match self.task {
+            Task::Climb => Some(self.climb.get_environment(ec)),
+            Task::RunRight | Task::GoalSeek | Task::Jump | Task::Carry => None,
+        };
+    }
+
+    pub fn get_seed(&self, generation: u32, trial: usize) -> u64 {
+        return self
+            .seed
+            .wrapping_add(generation as u64 * 1000)
+            .wrapping_add(trial as u64);
+    }
+}
+
+// Marks entities spawned by a task
+#[derive(Component)]
+pub struct TaskEntity;
+
+// Task progress of every spawned organism
+#[derive(Resource, Default)]
+pub struct TaskState {
+    last_elapsed: f32,
+    // Extra brain inputs for each organism this tick
+    pub stimuli: Vec<Vec<f32>>,
+    // Score of each organism in the running trial
+    pub scores: Vec<f32>,
+    goals: Vec<GoalProgress>,
+    jumps: Vec<JumpProgress>,
+    carries: Vec<CarryProgress>,
+    ents: Vec<Entity>,
+}
+impl TaskState {
+    pub fn despawn(&mut self, commands: &mut Commands) {
+        for e in self.ents.iter() {
+            commands.entity(*e).despawn();
+        }
+        self.ents.clear();
+        self.stimuli.clear();
+        self.scores.clear();
+        self.goals.clear();
+        self.jumps.clear();
+        self.carries.clear();
+    }
+
+    // Scores of the running trial, None when organisms are scored by distance travelled
+    pub fn get_scores(&self, tc: &TaskConfig, num_organisms: usize) -> Option<Vec<f32>> {
+        if tc.task == Task::RunRight {
+            return None;
+        }
+        let mut scores = self.scores.clone();
+        scores.resize(num_organisms, 0.0);
+        return Some(scores);
+    }
+}
+
+// Mean position of an organism's joints
+pub fn get_centre(o: &Organism, joints: &Query<&Transform, With<Joint>>) -> Vec2 {
+    return o
+        .joints
+        .iter()
+        .filter_map(|j| joints.get(*j).ok())
+        .map(|t| t.translation.truncate())
+        .sum::<Vec2>()
+        / o.joints.len().max(1) as f32;
+}
+
+// Reset the task when a new trial spawns and update stimuli and scores every tick
+pub fn update_task(
+    mut commands: Commands,
+    tc: Res<TaskConfig>,
+    gc: Res<GenerationConfig>,
+    trials: Res<Trials>,
+    ol: Res<OrganismList>,
+    handles: Res<Handles>,
+    mut ts: ResMut<TaskState>,
+    joints: Query<&Transform, With<Joint>>,
+    mut task_ents: Query<&mut Transform, (With<TaskEntity>, Without<Joint>)>,
+) {
+    if tc.task == Task::RunRight || !ol.is_spawned {
+        return;
+    }
+
+    // The timer resets whenever a new trial or generation is spawned
+    let elapsed_secs = gc.timer.elapsed_secs();
+    if elapsed_secs < ts.last_elapsed || ts.scores.len() != ol.organisms.len() {
+        ts.despawn(&mut commands);
+        ts.scores = vec![0.0; ol.organisms.len()];
+        ts.stimuli = vec![vec![0.0; tc.task.get_num_stimuli()]; ol.organisms.len()];
+        match tc.task {
+            Task::RunRight | Task::Climb => {}
+            Task::GoalSeek => reset_goals(&mut commands, &tc, &gc, &trials, &ol, &handles, &mut ts),
+            Task::Jump => reset_jumps(&ol, &mut ts),
+            Task::Carry => reset_carries(&mut commands, &tc, &ol, &handles, &mut ts),
+        }
+    }
+    ts.last_elapsed = elapsed_secs;
+
+    match tc.task {
+        Task::RunRight => {}
+        Task::GoalSeek => update_goals(&tc, &ol, &mut ts, &joints, &mut task_ents),
+        Task::Jump => update_jumps(&tc, &gc, &ol, &mut ts, &joints),
+        Task::Climb => update_climbs(&tc, &ol, &mut ts, &joints),
+        Task::Carry => update_carries(&tc, &ol, &mut ts, &joints, &task_ents),
+    }
+}
+
+pub struct TaskPlugin;
+impl Plugin for TaskPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TaskState::default()).add_systems(
+            Update,
+            update_task.before(update_brains).run_if(is_simulation),
+        );
+    }
+}